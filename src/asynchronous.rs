@@ -0,0 +1,161 @@
+//! Asynchronous equivalents of [`EasyCommand`]'s methods, for use with [`tokio`].
+
+use std::{
+    io,
+    process::{ExitStatus, Output},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{
+    check_status, EasyCommand, ExecuteError, OutputErrorKind, RunErrorKind, SpawnAndWaitErrorKind,
+};
+
+/// These methods spawn child processes just like their blocking counterparts, and then wait upon
+/// them (and read their output) on [`tokio`]'s blocking thread pool. They produce the same errors
+/// and logs as their blocking counterparts.
+///
+/// If a returned future is dropped before completion, the child process is terminated in the
+/// background, according to its [`TerminationPolicy`](crate::TerminationPolicy).
+impl EasyCommand {
+    /// The asynchronous equivalent of [`Self::spawn_and_wait`].
+    pub async fn spawn_and_wait_async(
+        &mut self,
+    ) -> Result<ExitStatus, ExecuteError<SpawnAndWaitErrorKind>> {
+        self.spawn_and_wait_async_impl()
+            .await
+            .map_err(|source| ExecuteError::new(self, source))
+    }
+
+    async fn spawn_and_wait_async_impl(&mut self) -> Result<ExitStatus, SpawnAndWaitErrorKind> {
+        log::debug!("spawning child process with {self}…");
+        let mut child = self
+            .spawn_running()
+            .map_err(|source| SpawnAndWaitErrorKind::Spawn { source })?;
+        let _abandon = AbandonOnDrop(child.abandoned_flag());
+        unblock(move || child.spawn_and_wait())
+            .await
+            .map_err(|source| SpawnAndWaitErrorKind::WaitForExitCode { source })?
+    }
+
+    /// The asynchronous equivalent of [`Self::run`].
+    pub async fn run_async(&mut self) -> Result<(), ExecuteError<RunErrorKind>> {
+        let status = self
+            .spawn_and_wait_async_impl()
+            .await
+            .map_err(RunErrorKind::from);
+        status
+            .and_then(check_status)
+            .map_err(|source| ExecuteError::new(self, source))
+    }
+
+    /// The asynchronous equivalent of [`Self::output`].
+    pub async fn output_async(&mut self) -> Result<Output, ExecuteError<OutputErrorKind>> {
+        self.output_async_impl().await.map_err(|source| {
+            let stderr_tail = source.stderr_tail();
+            ExecuteError::new(self, source).with_output_tail(stderr_tail)
+        })
+    }
+
+    async fn output_async_impl(&mut self) -> Result<Output, OutputErrorKind> {
+        log::debug!("getting output from {self}…");
+        let mut child = self
+            .spawn_piped()
+            .map_err(|source| OutputErrorKind::Spawn { source })?;
+        let _abandon = AbandonOnDrop(child.abandoned_flag());
+        unblock(move || child.output())
+            .await
+            .map_err(|source| OutputErrorKind::WaitForExitCode { source })?
+    }
+}
+
+/// Abandons a child process being waited upon when dropped, so that, if that is before waiting
+/// completes, the child process is terminated.
+struct AbandonOnDrop(Arc<AtomicBool>);
+
+impl Drop for AbandonOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Runs `f` on [`tokio`]'s blocking thread pool, propagating any panic.
+///
+/// Returns an error if the runtime shut down before `f` could run, in which case it was dropped.
+async fn unblock<T>(f: impl FnOnce() -> T + Send + 'static) -> io::Result<T>
+where
+    T: Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(t) => Ok(t),
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(io::Error::other(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::{poll_fn, Future},
+        pin::pin,
+        task::Poll,
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn dropping_future_terminates_child() {
+        for output in [false, true] {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .build()
+                .unwrap();
+            let task = rt.spawn(async move {
+                let mut cmd = EasyCommand::simple("sleep", ["10"]);
+                if output {
+                    cmd.output_async().await.map(drop).map_err(drop)
+                } else {
+                    cmd.run_async().await.map_err(drop)
+                }
+            });
+            // Let the task spawn the child process and start waiting on it.
+            rt.block_on(tokio::task::yield_now());
+            task.abort();
+            assert!(rt.block_on(task).unwrap_err().is_cancelled());
+
+            let started = Instant::now();
+            // This waits for the child process to be waited upon on the blocking thread pool.
+            drop(rt);
+            assert!(started.elapsed() < Duration::from_secs(5));
+        }
+    }
+
+    #[test]
+    fn unblock_fails_if_runtime_shuts_down() {
+        let shut_down = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let handle = shut_down.handle().clone();
+        drop(shut_down);
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let result = rt.block_on(async {
+            let mut task = pin!(unblock(|| ()));
+            // Start the task on the runtime that has shut down.
+            let poll = poll_fn(|cx| {
+                let _guard = handle.enter();
+                Poll::Ready(task.as_mut().poll(cx))
+            });
+            match poll.await {
+                Poll::Ready(result) => result,
+                Poll::Pending => task.await,
+            }
+        });
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,452 @@
+//! The machinery for waiting on child processes spawned by an [`EasyCommand`](crate::EasyCommand).
+
+use std::{
+    io::{self, Read},
+    process::{ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    executor::Process,
+    input::{PendingInput, WriteInputError},
+    EasyCommandInvocation, TerminationPolicy, TerminationStage,
+};
+
+/// The longest we sleep between polls for the exit of a child process with a deadline, or that
+/// may be abandoned.
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long we keep reading output from a child process after killing it.
+///
+/// If the child process has spawned processes of its own, they may hold its `stdout` and `stderr`
+/// open indefinitely, so we can't wait for them to be closed.
+const DRAIN_AFTER_KILL: Duration = Duration::from_millis(100);
+
+/// How long we wait for input to finish being written to a child process after it exits.
+///
+/// Like with [`DRAIN_AFTER_KILL`], processes spawned by the child process may hold its `stdin`
+/// open, and keep reading it, indefinitely.
+const INPUT_AFTER_EXIT: Duration = Duration::from_millis(100);
+
+/// One of the output streams of a child process.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// A spawned child process, along with everything needed to wait on it and report about it.
+pub(crate) struct Running {
+    child: Box<dyn Process>,
+    cmd: EasyCommandInvocation,
+    started: Instant,
+    deadline: Option<Instant>,
+    termination: TerminationPolicy,
+    /// The result of writing input to the child process, if any is being written.
+    input: Option<mpsc::Receiver<Result<(), WriteInputError>>>,
+    /// How long the child process had run when it timed out, and how it was stopped, if it did.
+    timed_out: Option<(Duration, TerminationStage)>,
+    /// Set once nothing awaits the result of waiting on the child process anymore.
+    abandoned: Option<Arc<AtomicBool>>,
+}
+
+/// A failure encountered while waiting on a [`Running`] child process.
+pub(crate) enum WaitError {
+    Wait(io::Error),
+    TimedOut {
+        elapsed: Duration,
+        stage: TerminationStage,
+    },
+    /// The child process exited successfully, with `status`, but its input could not be written.
+    WriteInput {
+        status: ExitStatus,
+        source: WriteInputError,
+    },
+}
+
+/// A failure encountered while waiting on a [`Running`] child process and reading its output.
+pub(crate) enum CollectError {
+    Wait(WaitError),
+    ReadOutput(io::Error),
+}
+
+impl From<WaitError> for CollectError {
+    fn from(e: WaitError) -> Self {
+        Self::Wait(e)
+    }
+}
+
+impl Running {
+    pub(crate) fn new(
+        child: Box<dyn Process>,
+        cmd: EasyCommandInvocation,
+        timeout: Option<Duration>,
+        termination: TerminationPolicy,
+    ) -> Self {
+        let started = Instant::now();
+        Self {
+            child,
+            cmd,
+            started,
+            deadline: timeout.map(|timeout| started + timeout),
+            termination,
+            input: None,
+            timed_out: None,
+            abandoned: None,
+        }
+    }
+
+    pub(crate) fn cmd(&self) -> &EasyCommandInvocation {
+        &self.cmd
+    }
+
+    pub(crate) fn take_stdin(&mut self) -> Option<Box<dyn io::Write + Send>> {
+        self.child.take_stdin()
+    }
+
+    /// Writes `input` to the `stdin` of the child process, if it is piped, in the background.
+    pub(crate) fn write_input(&mut self, input: PendingInput) {
+        if let Some(stdin) = self.child.take_stdin() {
+            self.input = Some(input.spawn_writer(stdin));
+        }
+    }
+
+    pub(crate) fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.child.take_stdout()
+    }
+
+    pub(crate) fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.child.take_stderr()
+    }
+
+    pub(crate) fn kill(&mut self) -> io::Result<()> {
+        self.child.kill()
+    }
+
+    pub(crate) fn take_stdout_as_stdio(&mut self) -> Option<Stdio> {
+        self.child.take_stdout_as_stdio()
+    }
+
+    /// A flag that, once set, has the child process terminated, as if its deadline had passed, by
+    /// whatever is waiting on it. For when nothing awaits the result of that anymore.
+    #[cfg(feature = "tokio")]
+    pub(crate) fn abandoned_flag(&mut self) -> Arc<AtomicBool> {
+        self.abandoned.get_or_insert_with(Default::default).clone()
+    }
+
+    /// Whether the child process should be terminated, as its deadline has passed, or it was
+    /// abandoned.
+    fn should_stop(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+            || self.is_abandoned()
+    }
+
+    fn is_abandoned(&self) -> bool {
+        self.abandoned
+            .as_ref()
+            .is_some_and(|abandoned| abandoned.load(Ordering::Relaxed))
+    }
+
+    /// When to next check whether the child process should be terminated, if ever.
+    fn next_check(&self) -> Option<Instant> {
+        let poll = self
+            .abandoned
+            .as_ref()
+            .map(|_| Instant::now() + MAX_POLL_INTERVAL);
+        match (self.deadline, poll) {
+            (Some(deadline), Some(poll)) => Some(deadline.min(poll)),
+            (deadline, poll) => deadline.or(poll),
+        }
+    }
+
+    /// Checks whether the child process has exited without blocking, terminating it if its
+    /// deadline has passed. Returns [`None`] if it is still running.
+    pub(crate) fn poll(&mut self) -> Option<Result<ExitStatus, WaitError>> {
+        if let Some(err) = self.timed_out() {
+            return Some(Err(err));
+        }
+        match self.child.try_wait() {
+            Ok(Some(status)) => Some(self.check_input(status)),
+            Ok(None) if self.should_stop() => Some(Err(self.time_out())),
+            Ok(None) => None,
+            Err(source) => Some(Err(WaitError::Wait(source))),
+        }
+    }
+
+    /// Waits for the child process to exit, terminating it if its deadline passes.
+    ///
+    /// Like [`Child::wait`](std::process::Child::wait), this closes any piped `stdin` that has not
+    /// been taken first, so that the child process does not wait for input forever.
+    pub(crate) fn wait(&mut self) -> Result<ExitStatus, WaitError> {
+        drop(self.child.take_stdin());
+        if let Some(err) = self.timed_out() {
+            return Err(err);
+        }
+        if self.abandoned.is_some() {
+            // Poll, rather than block, so as to notice being abandoned.
+            let mut poll_interval = Duration::from_millis(1);
+            loop {
+                if let Some(result) = self.poll() {
+                    return result;
+                }
+                thread::sleep(poll_interval);
+                poll_interval = (poll_interval * 2).min(MAX_POLL_INTERVAL);
+            }
+        }
+        match wait_until(&mut *self.child, self.deadline) {
+            Ok(Some(status)) => self.check_input(status),
+            Ok(None) => Err(self.time_out()),
+            Err(source) => Err(WaitError::Wait(source)),
+        }
+    }
+
+    /// Reads `stdout` and `stderr` of the child process (if piped) until they are closed, handing
+    /// each chunk read to `on_chunk` in the order received, and then waits for the child process
+    /// to exit. Like [`Self::wait`], this closes any piped `stdin` that has not been taken first.
+    ///
+    /// Both streams are read concurrently, so neither can fill up and block the child process.
+    pub(crate) fn wait_with_output(
+        &mut self,
+        mut on_chunk: impl FnMut(Stream, &[u8]),
+    ) -> Result<ExitStatus, CollectError> {
+        drop(self.child.take_stdin());
+        let (tx, rx) = mpsc::channel();
+        let mut open_streams = 0;
+        if let Some(stdout) = self.child.take_stdout() {
+            spawn_reader(Stream::Stdout, stdout, tx.clone());
+            open_streams += 1;
+        }
+        if let Some(stderr) = self.child.take_stderr() {
+            spawn_reader(Stream::Stderr, stderr, tx.clone());
+            open_streams += 1;
+        }
+        drop(tx);
+
+        let mut handle_event = |event, open_streams: &mut usize| match event {
+            ReaderEvent::Data(stream, data) => {
+                on_chunk(stream, &data);
+                Ok(())
+            }
+            ReaderEvent::Closed => {
+                *open_streams -= 1;
+                Ok(())
+            }
+            ReaderEvent::Failed(source) => Err(CollectError::ReadOutput(source)),
+        };
+
+        while open_streams > 0 {
+            let event = match self.next_check() {
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                Some(check) => rx.recv_timeout(check.saturating_duration_since(Instant::now())),
+            };
+            match event {
+                Ok(event) => handle_event(event, &mut open_streams)?,
+                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) if !self.should_stop() => (),
+                Err(RecvTimeoutError::Timeout) => {
+                    let err = self.time_out();
+                    let drain_deadline = Instant::now() + DRAIN_AFTER_KILL;
+                    while open_streams > 0 {
+                        let timeout = drain_deadline.saturating_duration_since(Instant::now());
+                        match rx.recv_timeout(timeout) {
+                            Ok(event) => handle_event(event, &mut open_streams)?,
+                            Err(_) => break,
+                        }
+                    }
+                    return Err(err.into());
+                }
+            }
+        }
+
+        Ok(self.wait()?)
+    }
+
+    /// Checks that all input was written to the child process, now that it has exited with
+    /// `status`.
+    ///
+    /// Failing to write input is only reported if the child process succeeded, since otherwise
+    /// its failure is the more likely cause, and more useful to report.
+    fn check_input(&mut self, status: ExitStatus) -> Result<ExitStatus, WaitError> {
+        let Some(input) = self.input.take() else {
+            return Ok(status);
+        };
+        match input.recv_timeout(INPUT_AFTER_EXIT) {
+            Ok(Err(source)) if status.success() => Err(WaitError::WriteInput { status, source }),
+            Ok(Err(e)) => {
+                log::debug!("failed to write input to `{}`: {e}", self.cmd);
+                Ok(status)
+            }
+            Ok(Ok(())) | Err(RecvTimeoutError::Disconnected) => Ok(status),
+            Err(RecvTimeoutError::Timeout) => {
+                log::debug!("still writing input to `{}` after it exited", self.cmd);
+                Ok(status)
+            }
+        }
+    }
+
+    /// Terminates the child process after its deadline has passed, recording that it timed out.
+    ///
+    /// This is also how an abandoned child process is terminated, in which case the error goes
+    /// unseen.
+    fn time_out(&mut self) -> WaitError {
+        let elapsed = self.started.elapsed();
+        if self.is_abandoned() {
+            log::debug!(
+                "`{}` was abandoned after {elapsed:?}, terminating it…",
+                self.cmd
+            );
+        } else {
+            log::warn!(
+                "`{}` timed out after {elapsed:?}, terminating it…",
+                self.cmd
+            );
+        }
+        let stage = self.terminate();
+        self.timed_out = Some((elapsed, stage));
+        WaitError::TimedOut { elapsed, stage }
+    }
+
+    /// The error to keep reporting for the child process, if it already timed out.
+    fn timed_out(&self) -> Option<WaitError> {
+        self.timed_out
+            .map(|(elapsed, stage)| WaitError::TimedOut { elapsed, stage })
+    }
+
+    /// Stops the child process according to its [`TerminationPolicy`].
+    pub(crate) fn terminate(&mut self) -> TerminationStage {
+        let Self {
+            child,
+            cmd,
+            termination,
+            ..
+        } = self;
+        let TerminationPolicy {
+            signal,
+            grace,
+            escalate,
+        } = *termination;
+
+        if let Some(signal) = signal.filter(|_| cfg!(unix)) {
+            match child.signal(signal) {
+                Ok(()) => {
+                    log::debug!("sent {signal} to `{cmd}`, waiting for it to exit…");
+                    let deadline = escalate.then(|| Instant::now() + grace);
+                    match wait_until(&mut **child, deadline) {
+                        Ok(Some(status)) => {
+                            log::debug!("received exit code {:?} from `{cmd}`", status.code());
+                            return TerminationStage::Signal(signal);
+                        }
+                        Ok(None) => {
+                            log::warn!("`{cmd}` did not exit within {grace:?}, killing it…")
+                        }
+                        Err(e) => {
+                            log::warn!("failed to wait for `{cmd}` to exit, killing it…: {e}")
+                        }
+                    }
+                }
+                Err(e) => log::warn!("failed to send {signal} to `{cmd}`, killing it…: {e}"),
+            }
+        }
+
+        match child.kill().and_then(|()| child.wait()) {
+            Ok(status) => {
+                log::debug!("killed `{cmd}`, received exit code {:?}", status.code());
+                TerminationStage::Kill
+            }
+            Err(e) => {
+                log::error!("failed to kill `{cmd}`: {e}");
+                TerminationStage::Failed
+            }
+        }
+    }
+}
+
+/// Waits for all `children` to exit, terminating each whose deadline passes.
+pub(crate) fn wait_all(children: &mut [Running]) -> Vec<Result<ExitStatus, WaitError>> {
+    for child in children.iter_mut() {
+        drop(child.take_stdin());
+    }
+    if children
+        .iter()
+        .all(|child| child.deadline.is_none() && child.abandoned.is_none())
+    {
+        return children.iter_mut().map(Running::wait).collect();
+    }
+
+    let mut results = children.iter().map(|_| None).collect::<Vec<_>>();
+    let mut poll_interval = Duration::from_millis(1);
+    loop {
+        let mut pending = false;
+        for (child, result) in children.iter_mut().zip(&mut results) {
+            if result.is_none() {
+                *result = child.poll();
+                pending |= result.is_none();
+            }
+        }
+        if !pending {
+            break;
+        }
+        thread::sleep(poll_interval);
+        poll_interval = (poll_interval * 2).min(MAX_POLL_INTERVAL);
+    }
+    results.into_iter().flatten().collect()
+}
+
+/// Waits for `child` to exit, polling if there is a `deadline`. Returns [`None`] if the deadline
+/// passes before then.
+fn wait_until(
+    child: &mut dyn Process,
+    deadline: Option<Instant>,
+) -> io::Result<Option<ExitStatus>> {
+    let Some(deadline) = deadline else {
+        return child.wait().map(Some);
+    };
+
+    let mut poll_interval = Duration::from_millis(1);
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(poll_interval.min(deadline - now));
+        poll_interval = (poll_interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
+enum ReaderEvent {
+    Data(Stream, Vec<u8>),
+    Closed,
+    Failed(io::Error),
+}
+
+fn spawn_reader(
+    stream: Stream,
+    mut reader: impl Read + Send + 'static,
+    tx: mpsc::Sender<ReaderEvent>,
+) {
+    thread::spawn(move || {
+        let mut buf = vec![0; 8 * 1024];
+        loop {
+            let event = match reader.read(&mut buf) {
+                Ok(0) => ReaderEvent::Closed,
+                Ok(len) => ReaderEvent::Data(stream, buf[..len].to_owned()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => ReaderEvent::Failed(e),
+            };
+            let done = !matches!(event, ReaderEvent::Data(..));
+            if tx.send(event).is_err() || done {
+                return;
+            }
+        }
+    });
+}
@@ -0,0 +1,156 @@
+use std::{
+    cell::RefCell,
+    sync::{PoisonError, RwLock},
+};
+
+use crate::executor::{FakeResponse, Process};
+
+/// Configuration for logging commands instead of executing them, i.e., for a `--dry-run` flag.
+///
+/// Under a dry run, no child process is spawned. Instead, the command is logged at the `info`
+/// level, and it succeeds with the configured output. A dry run can be enabled for a single
+/// command with [`EasyCommand::dry_run`](crate::EasyCommand::dry_run), for the whole process with
+/// [`DryRun::set_global`], or for the current thread within a scope with [`DryRun::scope`].
+#[derive(Clone, Debug)]
+pub struct DryRun {
+    response: FakeResponse,
+}
+
+static GLOBAL: RwLock<Option<DryRun>> = RwLock::new(None);
+
+thread_local! {
+    static SCOPED: RefCell<Option<DryRun>> = const { RefCell::new(None) };
+}
+
+impl DryRun {
+    /// A dry run where commands succeed without output.
+    pub fn new() -> Self {
+        Self {
+            response: FakeResponse::success(),
+        }
+    }
+
+    /// Pretend commands wrote `stdout` to `stdout`, if it is captured.
+    pub fn stdout(self, stdout: impl Into<Vec<u8>>) -> Self {
+        Self {
+            response: self.response.stdout(stdout),
+        }
+    }
+
+    /// Pretend commands wrote `stderr` to `stderr`, if it is captured.
+    pub fn stderr(self, stderr: impl Into<Vec<u8>>) -> Self {
+        Self {
+            response: self.response.stderr(stderr),
+        }
+    }
+
+    /// Set a dry run for all commands in this process, or unset it with [`None`].
+    pub fn set_global(dry_run: Option<Self>) {
+        *GLOBAL.write().unwrap_or_else(PoisonError::into_inner) = dry_run;
+    }
+
+    /// Use this dry run for all commands executed on the current thread while running `f`. This
+    /// takes precedence over [`Self::set_global`].
+    pub fn scope<T>(self, f: impl FnOnce() -> T) -> T {
+        struct Restore(Option<DryRun>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let previous = self.0.take();
+                SCOPED.with(|scoped| *scoped.borrow_mut() = previous);
+            }
+        }
+
+        let _restore = Restore(SCOPED.with(|scoped| scoped.borrow_mut().replace(self)));
+        f()
+    }
+
+    /// The dry run in effect for a command, if any.
+    pub(crate) fn resolve(dry_run: Option<&Self>) -> Option<Self> {
+        dry_run
+            .cloned()
+            .or_else(|| SCOPED.with(|scoped| scoped.borrow().clone()))
+            .or_else(|| {
+                GLOBAL
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .clone()
+            })
+    }
+
+    /// Logs that the command displayed as `command_line` is not being executed, and pretends that
+    /// it was.
+    pub(crate) fn skip(&self, command_line: &str) -> Box<dyn Process> {
+        log::info!("dry run: skipping execution of `{command_line}`");
+        self.response.clone().into_process()
+    }
+}
+
+impl Default for DryRun {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{panic, sync::Arc};
+
+    use super::*;
+    use crate::{
+        executor::{FakeExecutor, FakeResponse},
+        EasyCommand,
+    };
+
+    /// A command that, unless under a dry run, is executed by the returned [`FakeExecutor`].
+    fn cmd() -> (EasyCommand, Arc<FakeExecutor>) {
+        let fake = Arc::new(FakeExecutor::new());
+        fake.on_program("deploy", FakeResponse::success().stdout("executed"));
+        let mut cmd = EasyCommand::new("deploy");
+        cmd.executor(fake.clone());
+        (cmd, fake)
+    }
+
+    fn stdout(cmd: &mut EasyCommand) -> String {
+        String::from_utf8(cmd.output().unwrap().stdout).unwrap()
+    }
+
+    #[test]
+    fn dry_run_spawns_nothing() {
+        let (mut cmd, fake) = cmd();
+        cmd.dry_run(DryRun::new().stdout("pretend").stderr("warning"));
+        let output = cmd.output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"pretend");
+        assert_eq!(output.stderr, b"warning");
+        cmd.run().unwrap();
+        assert!(fake.executed().is_empty());
+    }
+
+    #[test]
+    fn command_dry_run_takes_precedence_over_scope() {
+        let (mut cmd, fake) = cmd();
+        DryRun::new().stdout("scoped").scope(|| {
+            assert_eq!(stdout(&mut cmd), "scoped");
+            cmd.dry_run(DryRun::new().stdout("own"));
+            assert_eq!(stdout(&mut cmd), "own");
+        });
+        assert!(fake.executed().is_empty());
+
+        let (mut cmd, fake) = self::cmd();
+        DryRun::new().scope(|| ());
+        assert_eq!(stdout(&mut cmd), "executed");
+        assert_eq!(fake.command_lines(), ["deploy"]);
+    }
+
+    #[test]
+    fn scope_restores_previous_dry_run_on_panic() {
+        let (mut cmd, _fake) = cmd();
+        DryRun::new().stdout("outer").scope(|| {
+            let result = panic::catch_unwind(|| DryRun::new().stdout("inner").scope(|| panic!()));
+            assert!(result.is_err());
+            assert_eq!(stdout(&mut cmd), "outer");
+        });
+        assert_eq!(stdout(&mut cmd), "executed");
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    io::{self, Read, Write},
+    process::{ExitStatus, Output},
+};
+
+use crate::{
+    child::Running, EasyCommand, ExecuteError, OutputErrorKind, SpawnAndWaitErrorKind, SpawnError,
+    TerminationStage,
+};
+
+/// A child process spawned in the background by [`EasyCommand::spawn`], like a
+/// [`std::process::Child`], whose errors name the command that spawned it.
+///
+/// The [`EasyCommand::timeout`] of the command is enforced only while waiting on the child process.
+/// Like a [`std::process::Child`], dropping this neither waits on nor kills the child process.
+pub struct EasyChild {
+    running: Running,
+}
+
+impl EasyCommand {
+    /// Spawn this command in the background, returning a handle to the child process without
+    /// waiting for it.
+    ///
+    /// Unlike [`Self::output`], this does not override the configuration of any streams, which are
+    /// inherited from the parent by default. Configure them to be piped with [`Self::stdout`] and
+    /// the like to access them with [`EasyChild::take_stdout`] and the like.
+    pub fn spawn(&mut self) -> Result<EasyChild, ExecuteError<SpawnError>> {
+        log::debug!("spawning child process with {self}…");
+        match self.spawn_running() {
+            Ok(running) => Ok(EasyChild { running }),
+            Err(source) => Err(ExecuteError::new(self, source)),
+        }
+    }
+}
+
+impl EasyChild {
+    /// Take the handle to the `stdin` of the child process, if it was piped, and not given an
+    /// [`Input`](crate::Input).
+    pub fn take_stdin(&mut self) -> Option<Box<dyn Write + Send>> {
+        self.running.take_stdin()
+    }
+
+    /// Take the handle to the `stdout` of the child process, if it was piped.
+    pub fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.running.take_stdout()
+    }
+
+    /// Take the handle to the `stderr` of the child process, if it was piped.
+    pub fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.running.take_stderr()
+    }
+
+    /// Check whether the child process has exited, without blocking, returning [`None`] if it is
+    /// still running.
+    ///
+    /// If the child process has outlived its timeout, it is terminated, and this returns
+    /// [`SpawnAndWaitErrorKind::TimedOut`], as do all later calls to this and [`Self::wait`].
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>, ExecuteError<SpawnAndWaitErrorKind>> {
+        self.running
+            .poll()
+            .transpose()
+            .map_err(|e| self.error(SpawnAndWaitErrorKind::from(e)))
+    }
+
+    /// Wait for the child process to exit, returning its exit status.
+    ///
+    /// Any piped `stdin` that has not been taken is closed first, so that the child process does
+    /// not wait for input forever.
+    pub fn wait(&mut self) -> Result<ExitStatus, ExecuteError<SpawnAndWaitErrorKind>> {
+        log::trace!("waiting for exit from `{}`…", self.running.cmd());
+        let status = self
+            .running
+            .wait()
+            .map_err(|e| self.error(SpawnAndWaitErrorKind::from(e)))?;
+        log::debug!(
+            "received exit code {:?} from `{}`",
+            status.code(),
+            self.running.cmd()
+        );
+        Ok(status)
+    }
+
+    /// Wait for the child process to exit, capturing whichever of its `stdout` and `stderr` were
+    /// piped, and not taken.
+    ///
+    /// Like [`Self::wait`], any piped `stdin` that has not been taken is closed first. On failure,
+    /// the returned error's [`Display`] implementation includes the last few lines of the captured
+    /// `stderr`.
+    pub fn wait_with_output(self) -> Result<Output, ExecuteError<OutputErrorKind>> {
+        log::trace!("waiting for output from `{}`…", self.running.cmd());
+        let cmd = self.running.cmd().clone();
+        self.running.output().map_err(|source| {
+            let stderr_tail = source.stderr_tail();
+            ExecuteError::for_invocation(cmd, source).with_output_tail(stderr_tail)
+        })
+    }
+
+    /// Kill the child process immediately, like [`std::process::Child::kill`], regardless of its
+    /// [`EasyCommand::termination`] policy; use [`Self::terminate`] to follow it instead.
+    ///
+    /// This does not wait for the child process to exit; use [`Self::wait`] for that.
+    pub fn kill(&mut self) -> Result<(), ExecuteError<io::Error>> {
+        log::debug!("killing `{}`…", self.running.cmd());
+        self.running.kill().map_err(|e| self.error(e))
+    }
+
+    /// Stop the child process according to its [`EasyCommand::termination`] policy, as is done
+    /// when it times out, and wait for it to exit, returning the stage of the policy that ended
+    /// it.
+    pub fn terminate(&mut self) -> TerminationStage {
+        log::debug!("terminating `{}`…", self.running.cmd());
+        self.running.terminate()
+    }
+
+    fn error<E>(&self, source: E) -> ExecuteError<E> {
+        ExecuteError::for_invocation(self.running.cmd().clone(), source)
+    }
+}
+
+impl Debug for EasyChild {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EasyChild")
+            .field("cmd", self.running.cmd())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Displays the command that spawned this child process, like [`EasyCommand`] does.
+impl Display for EasyChild {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let cmd = self.running.cmd();
+        if f.alternate() {
+            write!(f, "{cmd}")
+        } else {
+            write!(f, "`{cmd}`")
+        }
+    }
+}
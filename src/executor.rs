@@ -0,0 +1,478 @@
+//! Pluggable backends for spawning the child processes of [`EasyCommand`]s.
+//!
+//! By default, child processes are spawned with [`std::process`] via [`SystemExecutor`]. Another
+//! [`Executor`] can be used for a single command with [`EasyCommand::executor`], or for everything
+//! executed on the current thread within a scope with [`with_default`].
+//!
+//! [`FakeExecutor`] is an [`Executor`] for unit tests. It spawns nothing, and instead responds to
+//! commands with canned exit statuses and output, recording which commands were executed.
+//!
+//! [`EasyCommand`]: crate::EasyCommand
+//! [`EasyCommand::executor`]: crate::EasyCommand::executor
+
+use std::{
+    cell::RefCell,
+    ffi::OsString,
+    io::{self, Cursor, Read, Write},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{resolve, Signal, SpawnError};
+
+/// Spawns child processes for [`EasyCommand`](crate::EasyCommand)s.
+pub trait Executor: Send + Sync {
+    /// Spawn a child process as described by `cmd`, whose streams have already been configured.
+    ///
+    /// `command_line` is `cmd` in shell syntax, with any secrets hidden, as displayed in errors and
+    /// logs. Use it, rather than `cmd`, to refer to the command in messages.
+    fn spawn(&self, cmd: &mut Command, command_line: &str) -> io::Result<Box<dyn Process>>;
+
+    /// Explain why [`Self::spawn`] failed to spawn `cmd` with `error`.
+    ///
+    /// `env_cleared` is whether the environment of `cmd` was cleared, as with
+    /// [`EasyCommand::env_clear`](crate::EasyCommand::env_clear), which [`Command`] does not tell.
+    ///
+    /// By default, `error` is reported as-is, with [`SpawnError::Other`].
+    fn classify_spawn_error(
+        &self,
+        cmd: &Command,
+        env_cleared: bool,
+        error: io::Error,
+    ) -> SpawnError {
+        let _ = (cmd, env_cleared);
+        SpawnError::Other(error)
+    }
+}
+
+/// A child process spawned by an [`Executor`].
+///
+/// The methods of this trait mirror those of [`Child`].
+pub trait Process: Send {
+    /// Take the handle to this process' `stdin`, if it was piped.
+    fn take_stdin(&mut self) -> Option<Box<dyn Write + Send>>;
+
+    /// Take the handle to this process' `stdout`, if it was piped.
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>>;
+
+    /// Take the handle to this process' `stderr`, if it was piped.
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>>;
+
+    /// Like [`Self::take_stdout`], but as a [`Stdio`] that can be connected directly to the
+    /// `stdin` of another child process.
+    ///
+    /// Returns [`None`] by default, in which case [`Self::take_stdout`] is used, and its contents
+    /// are copied to the other child process.
+    fn take_stdout_as_stdio(&mut self) -> Option<Stdio> {
+        None
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>>;
+
+    fn wait(&mut self) -> io::Result<ExitStatus>;
+
+    fn kill(&mut self) -> io::Result<()>;
+
+    /// Send `signal` to this process. Unsupported by default.
+    fn signal(&mut self, signal: Signal) -> io::Result<()> {
+        let _ = signal;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "sending signals is not supported",
+        ))
+    }
+}
+
+/// The default [`Executor`], which spawns child processes with [`Command::spawn`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemExecutor;
+
+impl Executor for SystemExecutor {
+    fn spawn(&self, cmd: &mut Command, _command_line: &str) -> io::Result<Box<dyn Process>> {
+        Ok(Box::new(cmd.spawn()?))
+    }
+
+    fn classify_spawn_error(
+        &self,
+        cmd: &Command,
+        env_cleared: bool,
+        error: io::Error,
+    ) -> SpawnError {
+        resolve::classify_spawn_error(cmd, env_cleared, error)
+    }
+}
+
+impl Process for Child {
+    fn take_stdin(&mut self) -> Option<Box<dyn Write + Send>> {
+        self.stdin.take().map(|stdin| Box::new(stdin) as _)
+    }
+
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stdout.take().map(|stdout| Box::new(stdout) as _)
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stderr.take().map(|stderr| Box::new(stderr) as _)
+    }
+
+    fn take_stdout_as_stdio(&mut self) -> Option<Stdio> {
+        self.stdout.take().map(Stdio::from)
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        Child::try_wait(self)
+    }
+
+    fn wait(&mut self) -> io::Result<ExitStatus> {
+        Child::wait(self)
+    }
+
+    fn kill(&mut self) -> io::Result<()> {
+        Child::kill(self)
+    }
+
+    #[cfg(unix)]
+    fn signal(&mut self, signal: Signal) -> io::Result<()> {
+        if self.try_wait()?.is_some() {
+            return Ok(());
+        }
+        let pid = libc::pid_t::try_from(self.id())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "PID out of range"))?;
+        // SAFETY: `kill` has no memory safety preconditions. This process has not been reaped
+        // yet, so `pid` still refers to it.
+        if unsafe { libc::kill(pid, signal.number()) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+thread_local! {
+    static DEFAULT: RefCell<Option<Arc<dyn Executor>>> = const { RefCell::new(None) };
+}
+
+/// Use `executor` for all [`EasyCommand`](crate::EasyCommand)s executed on the current thread
+/// while running `f`, unless they were given one with
+/// [`EasyCommand::executor`](crate::EasyCommand::executor).
+pub fn with_default<T>(executor: Arc<dyn Executor>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<Arc<dyn Executor>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            DEFAULT.with(|default| *default.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(DEFAULT.with(|default| default.borrow_mut().replace(executor)));
+    f()
+}
+
+pub(crate) fn resolve(executor: Option<&Arc<dyn Executor>>) -> Arc<dyn Executor> {
+    executor
+        .cloned()
+        .or_else(|| DEFAULT.with(|default| default.borrow().clone()))
+        .unwrap_or_else(|| Arc::new(SystemExecutor))
+}
+
+/// An [`Executor`] for unit tests that spawns nothing, and instead responds to commands with
+/// canned exit statuses and output.
+///
+/// Responses are chosen by the first rule added that matches a command. Commands that match no
+/// rule fail to spawn with [`io::ErrorKind::NotFound`].
+#[derive(Debug, Default)]
+pub struct FakeExecutor {
+    rules: Mutex<Vec<(FakeRule, FakeResponse)>>,
+    executed: Mutex<Vec<FakeExecution>>,
+}
+
+#[derive(Debug)]
+enum FakeRule {
+    Program(OsString),
+    ProgramWithArgs(OsString, Vec<OsString>),
+}
+
+impl FakeRule {
+    fn matches(&self, cmd: &Command) -> bool {
+        match self {
+            Self::Program(program) => cmd.get_program() == program,
+            Self::ProgramWithArgs(program, args) => {
+                cmd.get_program() == program
+                    && cmd.get_args().eq(args.iter().map(OsString::as_os_str))
+            }
+        }
+    }
+}
+
+impl FakeExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Respond to commands running `program` with any arguments.
+    pub fn on_program<P>(&self, program: P, response: FakeResponse) -> &Self
+    where
+        P: Into<OsString>,
+    {
+        self.add_rule(FakeRule::Program(program.into()), response)
+    }
+
+    /// Respond to commands running `program` with exactly `args`.
+    pub fn on<P, A, I>(&self, program: P, args: I, response: FakeResponse) -> &Self
+    where
+        P: Into<OsString>,
+        A: Into<OsString>,
+        I: IntoIterator<Item = A>,
+    {
+        let args = args.into_iter().map(Into::into).collect();
+        self.add_rule(FakeRule::ProgramWithArgs(program.into(), args), response)
+    }
+
+    fn add_rule(&self, rule: FakeRule, response: FakeResponse) -> &Self {
+        lock(&self.rules).push((rule, response));
+        self
+    }
+
+    /// All commands executed so far, in order, including those that matched no rule.
+    pub fn executed(&self) -> Vec<FakeExecution> {
+        lock(&self.executed).clone()
+    }
+
+    /// Like [`Self::executed`], but as command lines in shell syntax, like `git commit -m 'a b'`.
+    pub fn command_lines(&self) -> Vec<String> {
+        lock(&self.executed)
+            .iter()
+            .map(|execution| execution.command_line.clone())
+            .collect()
+    }
+}
+
+impl Executor for FakeExecutor {
+    fn spawn(&self, cmd: &mut Command, command_line: &str) -> io::Result<Box<dyn Process>> {
+        lock(&self.executed).push(FakeExecution {
+            program: cmd.get_program().to_owned(),
+            args: cmd.get_args().map(ToOwned::to_owned).collect(),
+            command_line: command_line.to_owned(),
+        });
+
+        let rules = lock(&self.rules);
+        let (_rule, response) = rules
+            .iter()
+            .find(|(rule, _response)| rule.matches(cmd))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("`{command_line}` matched no rule of `FakeExecutor`"),
+                )
+            })?;
+        Ok(response.clone().into_process())
+    }
+}
+
+/// A command executed by a [`FakeExecutor`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FakeExecution {
+    pub program: OsString,
+    /// The arguments, including any secrets, verbatim.
+    pub args: Vec<OsString>,
+    /// The command in shell syntax, as displayed in errors, with any secrets hidden.
+    pub command_line: String,
+}
+
+/// The exit status and output with which a [`FakeExecutor`] responds to a command.
+#[derive(Clone, Debug)]
+pub struct FakeResponse {
+    status: ExitStatus,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl FakeResponse {
+    /// Exit successfully, without output.
+    pub fn success() -> Self {
+        Self::exit_code(0)
+    }
+
+    /// Exit with `code`, without output.
+    pub fn exit_code(code: i32) -> Self {
+        Self::with_status(exit_status_from_code(code))
+    }
+
+    /// Be killed by `signal`, without output.
+    #[cfg(unix)]
+    pub fn signal(signal: Signal) -> Self {
+        use std::os::unix::process::ExitStatusExt;
+
+        Self::with_status(ExitStatus::from_raw(signal.number()))
+    }
+
+    fn with_status(status: ExitStatus) -> Self {
+        Self {
+            status,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+        }
+    }
+
+    /// Write `stdout` to `stdout`, if it is captured.
+    pub fn stdout(self, stdout: impl Into<Vec<u8>>) -> Self {
+        Self {
+            stdout: stdout.into(),
+            ..self
+        }
+    }
+
+    /// Write `stderr` to `stderr`, if it is captured.
+    pub fn stderr(self, stderr: impl Into<Vec<u8>>) -> Self {
+        Self {
+            stderr: stderr.into(),
+            ..self
+        }
+    }
+
+    pub(crate) fn into_process(self) -> Box<dyn Process> {
+        let Self {
+            status,
+            stdout,
+            stderr,
+        } = self;
+        Box::new(FakeProcess {
+            status,
+            stdin: Some(io::sink()),
+            stdout: Some(stdout),
+            stderr: Some(stderr),
+        })
+    }
+}
+
+#[cfg(unix)]
+fn exit_status_from_code(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+
+    ExitStatus::from_raw((code & 0xff) << 8)
+}
+
+#[cfg(windows)]
+fn exit_status_from_code(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+
+    ExitStatus::from_raw(code as u32)
+}
+
+struct FakeProcess {
+    status: ExitStatus,
+    stdin: Option<io::Sink>,
+    stdout: Option<Vec<u8>>,
+    stderr: Option<Vec<u8>>,
+}
+
+impl Process for FakeProcess {
+    fn take_stdin(&mut self) -> Option<Box<dyn Write + Send>> {
+        self.stdin.take().map(|stdin| Box::new(stdin) as _)
+    }
+
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stdout
+            .take()
+            .map(|stdout| Box::new(Cursor::new(stdout)) as _)
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stderr
+            .take()
+            .map(|stderr| Box::new(Cursor::new(stderr)) as _)
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        Ok(Some(self.status))
+    }
+
+    fn wait(&mut self) -> io::Result<ExitStatus> {
+        Ok(self.status)
+    }
+
+    fn kill(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EasyCommand, RunErrorKind, SpawnAndWaitErrorKind};
+
+    fn cmd(fake: &Arc<FakeExecutor>, program: &str, args: &[&str]) -> EasyCommand {
+        let mut cmd = EasyCommand::simple(program, args);
+        cmd.executor(fake.clone());
+        cmd
+    }
+
+    #[test]
+    fn fake_responds_by_first_matching_rule() {
+        let fake = Arc::new(FakeExecutor::new());
+        fake.on("git", ["status"], FakeResponse::success().stdout("clean"))
+            .on_program("git", FakeResponse::exit_code(1).stderr("no"));
+
+        let output = cmd(&fake, "git", &["status"]).output().unwrap();
+        assert_eq!(output.stdout, b"clean");
+        let output = cmd(&fake, "git", &["push"]).output().unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        assert_eq!(output.stderr, b"no");
+    }
+
+    #[test]
+    fn fake_fails_to_spawn_commands_matching_no_rule() {
+        let fake = Arc::new(FakeExecutor::new());
+        let e = cmd(&fake, "docker", &["ps"]).run().unwrap_err();
+        match e.source {
+            RunErrorKind::SpawnAndWait(SpawnAndWaitErrorKind::Spawn {
+                source: SpawnError::Other(e),
+            }) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn fake_records_executed_commands() {
+        let fake = Arc::new(FakeExecutor::new());
+        fake.on_program("git", FakeResponse::success());
+        cmd(&fake, "git", &["commit", "-m", "a b"]).run().unwrap();
+        let _ = cmd(&fake, "docker", &[]).run();
+        assert_eq!(fake.command_lines(), ["git commit -m 'a b'", "docker"]);
+        assert_eq!(
+            fake.executed()[0].args,
+            [
+                OsString::from("commit"),
+                OsString::from("-m"),
+                OsString::from("a b")
+            ]
+        );
+    }
+
+    #[test]
+    fn fake_reports_unsuccessful_exit_codes() {
+        let fake = Arc::new(FakeExecutor::new());
+        fake.on_program("false", FakeResponse::exit_code(3));
+        let e = cmd(&fake, "false", &[]).run().unwrap_err();
+        assert!(matches!(
+            e.source,
+            RunErrorKind::UnsuccessfulExitCode { code: Some(3), .. }
+        ));
+        assert!(cmd(&fake, "false", &[]).output_checked().is_err());
+    }
+
+    #[test]
+    fn default_executor_applies_within_scope() {
+        let fake = Arc::new(FakeExecutor::new());
+        fake.on_program("git", FakeResponse::success());
+        with_default(fake.clone(), || {
+            EasyCommand::simple("git", ["fetch"]).run().unwrap();
+        });
+        assert_eq!(fake.command_lines(), ["git fetch"]);
+    }
+}
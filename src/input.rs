@@ -0,0 +1,223 @@
+use std::{
+    fmt::{self, Debug, Formatter},
+    fs::File,
+    io::{self, Cursor, Read, Write},
+    path::PathBuf,
+    process::Stdio,
+    sync::{mpsc, Arc, Mutex, PoisonError},
+    thread,
+};
+
+use crate::{spec::StdioConfig, EasyCommand, StdioSpec};
+
+/// What to write to the `stdin` of a child process, set with [`EasyCommand::input`].
+///
+/// Input is written on a separate thread while the child process runs, so it can be combined with
+/// methods that capture output, like [`EasyCommand::output`], without risk of deadlock.
+pub struct Input {
+    source: Source,
+    require_all_read: bool,
+}
+
+enum Source {
+    Bytes(Arc<[u8]>),
+    File(PathBuf),
+    /// Taken by the first execution, after which there is no more input.
+    Reader(Mutex<Option<Box<dyn Read + Send>>>),
+}
+
+impl Input {
+    /// Write `bytes`. This can be used for any number of executions.
+    pub fn bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        Self::new(Source::Bytes(bytes.into().into()))
+    }
+
+    /// Write the contents of the file at `path`, which is opened anew for each execution.
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Self::new(Source::File(path.into()))
+    }
+
+    /// Write whatever can be read from `reader`, until the end.
+    ///
+    /// Since `reader` can only be read once, only the first execution gets this input, and later
+    /// ones get an empty `stdin`.
+    pub fn reader(reader: impl Read + Send + 'static) -> Self {
+        Self::new(Source::Reader(Mutex::new(Some(Box::new(reader)))))
+    }
+
+    fn new(source: Source) -> Self {
+        Self {
+            source,
+            require_all_read: false,
+        }
+    }
+
+    /// Report [`WriteInputError::BrokenPipe`] if the child process exits successfully without
+    /// reading all of this input.
+    ///
+    /// By default, that is not an error, as in a shell pipeline like `yes | head`, since many
+    /// programs only read as much input as they need.
+    pub fn require_all_read(self) -> Self {
+        Self {
+            require_all_read: true,
+            ..self
+        }
+    }
+
+    /// Takes what to write for a single execution.
+    pub(crate) fn take(&mut self) -> PendingInput {
+        let source = match &mut self.source {
+            Source::Bytes(bytes) => PendingSource::Bytes(bytes.clone()),
+            Source::File(path) => PendingSource::File(path.clone()),
+            Source::Reader(reader) => PendingSource::Reader(
+                reader
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .take(),
+            ),
+        };
+        PendingInput {
+            source,
+            require_all_read: self.require_all_read,
+        }
+    }
+}
+
+impl Debug for Input {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            Source::Bytes(bytes) => write!(f, "Input::bytes(<{} bytes>)", bytes.len())?,
+            Source::File(path) => write!(f, "Input::file({path:?})")?,
+            Source::Reader(_) => write!(f, "Input::reader(..)")?,
+        }
+        if self.require_all_read {
+            write!(f, ".require_all_read()")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<u8>> for Input {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for Input {
+    fn from(bytes: &[u8]) -> Self {
+        Self::bytes(bytes)
+    }
+}
+
+impl From<String> for Input {
+    fn from(s: String) -> Self {
+        Self::bytes(s)
+    }
+}
+
+impl From<&str> for Input {
+    fn from(s: &str) -> Self {
+        Self::bytes(s)
+    }
+}
+
+impl EasyCommand {
+    /// Write `input` to the `stdin` of the child process, then close it.
+    ///
+    /// Like a redirection in a shell, this takes precedence over methods that would otherwise
+    /// connect `stdin`, like [`Self::output`], which otherwise makes it null, and
+    /// [`Self::pipe`]. Calling [`Self::stdin`] afterwards removes `input`.
+    ///
+    /// Whether the child process must read all of `input` is up to [`Input::require_all_read`].
+    pub fn input(&mut self, input: impl Into<Input>) -> &mut Self {
+        self.inner.stdin(Stdio::piped());
+        self.stdio.stdin = StdioConfig::Spec(StdioSpec::Piped);
+        self.input = Some(input.into());
+        self
+    }
+}
+
+/// What to write to the `stdin` of a single child process.
+pub(crate) struct PendingInput {
+    source: PendingSource,
+    require_all_read: bool,
+}
+
+enum PendingSource {
+    Bytes(Arc<[u8]>),
+    File(PathBuf),
+    Reader(Option<Box<dyn Read + Send>>),
+}
+
+impl PendingSource {
+    fn open(self) -> Result<Box<dyn Read + Send>, WriteInputError> {
+        Ok(match self {
+            Self::Bytes(bytes) => Box::new(Cursor::new(bytes)),
+            Self::File(path) => match File::open(&path) {
+                Ok(file) => Box::new(file),
+                Err(source) => return Err(WriteInputError::OpenFile { path, source }),
+            },
+            Self::Reader(reader) => reader.unwrap_or_else(|| Box::new(io::empty())),
+        })
+    }
+}
+
+impl PendingInput {
+    /// Writes all of this input to `stdin` on another thread, then closes it. The result is sent
+    /// on the returned channel.
+    pub(crate) fn spawn_writer(
+        self,
+        mut stdin: Box<dyn Write + Send>,
+    ) -> mpsc::Receiver<Result<(), WriteInputError>> {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let Self {
+                source,
+                require_all_read,
+            } = self;
+            let result = source
+                .open()
+                .and_then(|mut reader| copy(&mut reader, &mut stdin));
+            drop(stdin);
+            let result = match result {
+                Err(WriteInputError::BrokenPipe) if !require_all_read => Ok(()),
+                result => result,
+            };
+            let _ = tx.send(result);
+        });
+        rx
+    }
+}
+
+fn copy(reader: &mut dyn Read, stdin: &mut dyn Write) -> Result<(), WriteInputError> {
+    let write_error = |source: io::Error| match source.kind() {
+        io::ErrorKind::BrokenPipe => WriteInputError::BrokenPipe,
+        _ => WriteInputError::Write { source },
+    };
+
+    let mut buf = vec![0; 8 * 1024];
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => return stdin.flush().map_err(write_error),
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(source) => return Err(WriteInputError::Read { source }),
+        };
+        stdin.write_all(&buf[..len]).map_err(write_error)?;
+    }
+}
+
+/// A failure to write an [`Input`] to the `stdin` of a child process.
+#[derive(Debug, thiserror::Error)]
+pub enum WriteInputError {
+    #[error("failed to open input file {}", path.display())]
+    OpenFile { path: PathBuf, source: io::Error },
+    #[error("failed to read input")]
+    Read { source: io::Error },
+    /// The child process closed `stdin`, usually by exiting, before all input was written. This
+    /// is only reported for [`Input::require_all_read`].
+    #[error("the child process closed `stdin` before all input was written")]
+    BrokenPipe,
+    #[error("failed to write to `stdin`")]
+    Write { source: io::Error },
+}
@@ -7,17 +7,71 @@
 //!   top.
 //! * Logging using the [`log`] crate.
 
+#[cfg(feature = "tokio")]
+mod asynchronous;
+mod child;
+mod dry_run;
+mod easy_child;
+pub mod executor;
+mod input;
+mod macros;
+mod parse;
+mod pipeline;
+mod redact;
+mod resolve;
+mod signal;
+mod spec;
+mod streaming;
+mod termination;
+mod transcript;
+mod version;
+
 use std::{
     ffi::OsStr,
     fmt::{self, Debug, Display, Formatter},
     io,
     iter::once,
-    process::{Command, ExitStatus, Output},
+    path::Path,
+    process::{Command, ExitStatus, Output, Stdio},
+    str::FromStr,
+    string::FromUtf8Error,
+    sync::Arc,
+    time::Duration,
+};
+
+use child::{CollectError, Running, WaitError};
+use executor::Executor;
+use redact::Secrets;
+use spec::{KnownStdio, StdioConfig};
+use streaming::TailBuffer;
+
+pub use child::Stream;
+pub use dry_run::DryRun;
+pub use easy_child::EasyChild;
+pub use input::{Input, WriteInputError};
+pub use pipeline::{Pipeline, PipelineErrorKind, PipelineStageErrorKind};
+pub use redact::Redaction;
+pub use resolve::{preflight, MissingProgramsError, ProgramNotFound, SpawnError};
+pub use signal::Signal;
+pub use spec::{CommandSpec, StdioSpec, ToSpecError};
+pub use streaming::{LogOutput, RunStreamingErrorKind};
+pub use termination::{TerminationPolicy, TerminationStage};
+pub use transcript::{OutputTranscriptErrorKind, Transcript, TranscriptChunk, TranscriptOutput};
+pub use version::{
+    ParseVersionError, ParseVersionReqError, Version, VersionErrorKind, VersionQuery, VersionReq,
 };
 
 /// A convenience API around [`Command`].
 pub struct EasyCommand {
     inner: Command,
+    timeout: Option<Duration>,
+    termination: TerminationPolicy,
+    executor: Option<Arc<dyn Executor>>,
+    dry_run: Option<DryRun>,
+    env_cleared: bool,
+    stdio: KnownStdio,
+    secrets: Secrets,
+    input: Option<Input>,
 }
 
 impl EasyCommand {
@@ -30,13 +84,30 @@ impl EasyCommand {
     }
 
     /// A convenience constructor that allows other method calls to be chained onto this one.
+    ///
+    /// Streams configured by `f` are only detected on a best-effort basis, from the alternate
+    /// [`Debug`] representation of [`Command`], which is not a stable interface, and
+    /// does not list them on all platforms. Where they are not detected, methods that capture or
+    /// connect streams, like [`Self::output`] and [`Self::pipe`], override them. To reliably keep
+    /// a stream's configuration, use [`Self::stdin`], [`Self::stdout`], and [`Self::stderr`]
+    /// instead.
     pub fn new_with<C>(cmd: C, f: impl FnOnce(&mut Command) -> &mut Command) -> Self
     where
         C: AsRef<OsStr>,
     {
         let mut cmd = Command::new(cmd);
         f(&mut cmd);
-        Self { inner: cmd }
+        Self {
+            stdio: KnownStdio::of_command(&cmd),
+            inner: cmd,
+            timeout: None,
+            termination: TerminationPolicy::default(),
+            executor: None,
+            dry_run: None,
+            env_cleared: false,
+            secrets: Secrets::default(),
+            input: None,
+        }
     }
 
     /// Like [`Self::new_with`], but optimized for ergonomic usage of an [`IntoIterator`] for
@@ -50,20 +121,298 @@ impl EasyCommand {
         Self::new_with(cmd, |cmd| cmd.args(args))
     }
 
+    /// Parse a command line in shell syntax, like `cargo test --features "a b"`, into a program
+    /// and its arguments, as [`shell_words::split`] does. Leading variable assignments, like
+    /// `RUSTFLAGS=-Dwarnings cargo build`, are set in the command's environment.
+    ///
+    /// No shell is involved: there is no support for expansions, redirections, or pipes.
+    ///
+    /// This is the inverse of the alternate form of [`Display`] (`{:#}`): parsing the displayed form
+    /// of a command yields an equivalent command, so long as it has no working directory or removed
+    /// or cleared environment variables, and is valid UTF-8. For instance, the above displays as
+    /// `cargo test --features 'a b'`. Commands with secrets, like those added with
+    /// [`Self::secret_arg`], do not round-trip, since their secrets are displayed as `***`.
+    ///
+    /// As in a shell, a word is only a variable assignment if its name and `=` are unquoted, so
+    /// `'A=b' prog` runs the program `A=b`.
+    pub fn parse(command_line: &str) -> Result<Self, ParseCommandError> {
+        let words =
+            parse::split(command_line).map_err(|source| ParseCommandError::UnbalancedQuotes {
+                command_line: command_line.to_owned(),
+                source,
+            })?;
+        let mut words = words.into_iter().peekable();
+        let mut envs = Vec::new();
+        while let Some(word) = words.next_if(|word| word.env_assignment().is_some()) {
+            let (key, value) = word.env_assignment().unwrap();
+            envs.push((key.to_owned(), value.to_owned()));
+        }
+        let mut words = words.map(parse::Word::into_text);
+        let program = words
+            .next()
+            .ok_or_else(|| ParseCommandError::MissingProgram {
+                command_line: command_line.to_owned(),
+            })?;
+        Ok(Self::new_with(program, |cmd| cmd.envs(envs).args(words)))
+    }
+
+    /// Equivalent to [`Command::arg`].
+    pub fn arg<A>(&mut self, arg: A) -> &mut Self
+    where
+        A: AsRef<OsStr>,
+    {
+        self.inner.arg(arg);
+        self
+    }
+
+    /// Equivalent to [`Command::args`].
+    pub fn args<A, I>(&mut self, args: I) -> &mut Self
+    where
+        A: AsRef<OsStr>,
+        I: IntoIterator<Item = A>,
+    {
+        self.inner.args(args);
+        self
+    }
+
+    /// Equivalent to [`Command::env`].
+    pub fn env<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.inner.env(key, value);
+        self
+    }
+
+    /// Equivalent to [`Command::envs`].
+    pub fn envs<K, V, I>(&mut self, vars: I) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.inner.envs(vars);
+        self
+    }
+
+    /// Equivalent to [`Command::env_remove`].
+    pub fn env_remove<K>(&mut self, key: K) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+    {
+        self.inner.env_remove(key);
+        self
+    }
+
+    /// Equivalent to [`Command::env_clear`].
+    pub fn env_clear(&mut self) -> &mut Self {
+        self.inner.env_clear();
+        self.env_cleared = true;
+        self
+    }
+
+    /// Like [`Self::arg`], but displayed as `***` in errors and logs.
+    pub fn secret_arg<A>(&mut self, arg: A) -> &mut Self
+    where
+        A: AsRef<OsStr>,
+    {
+        self.secrets.args.insert(self.inner.get_args().len());
+        self.arg(arg)
+    }
+
+    /// Like [`Self::env`], but with `value` displayed as `***` in errors and logs.
+    pub fn secret_env<K, V>(&mut self, key: K, value: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.secrets.envs.insert(key.as_ref().to_owned());
+        self.env(key, value)
+    }
+
+    /// Hide whatever `redaction` matches in this command when it is displayed in errors and logs,
+    /// in addition to any global [`Redaction`]s.
+    pub fn redact(&mut self, redaction: Redaction) -> &mut Self {
+        self.secrets.redactions.push(redaction);
+        self
+    }
+
+    /// Equivalent to [`Command::current_dir`].
+    pub fn current_dir<P>(&mut self, dir: P) -> &mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.inner.current_dir(dir);
+        self
+    }
+
+    /// Equivalent to [`Command::stdin`].
+    ///
+    /// Only streams configured with a [`StdioSpec`] can be described by [`Self::to_spec`].
+    ///
+    /// This takes precedence over methods that would otherwise connect this stream, like
+    /// [`Self::output`] and [`Self::pipe`], as with redirections in a shell. This removes any
+    /// [`Input`] set with [`Self::input`].
+    pub fn stdin<T>(&mut self, cfg: T) -> &mut Self
+    where
+        T: Into<Stdio> + 'static,
+    {
+        self.stdio.stdin = StdioConfig::of(&cfg);
+        self.inner.stdin(cfg);
+        self.input = None;
+        self
+    }
+
+    /// Equivalent to [`Command::stdout`].
+    ///
+    /// This takes precedence over methods that would otherwise capture or connect this stream,
+    /// like [`Self::output`] and [`Self::pipe`], as with redirections in a shell.
+    pub fn stdout<T>(&mut self, cfg: T) -> &mut Self
+    where
+        T: Into<Stdio> + 'static,
+    {
+        self.stdio.stdout = StdioConfig::of(&cfg);
+        self.inner.stdout(cfg);
+        self
+    }
+
+    /// Equivalent to [`Command::stderr`].
+    ///
+    /// This takes precedence over methods that would otherwise capture this stream, like
+    /// [`Self::output`], as with redirections in a shell.
+    pub fn stderr<T>(&mut self, cfg: T) -> &mut Self
+    where
+        T: Into<Stdio> + 'static,
+    {
+        self.stdio.stderr = StdioConfig::of(&cfg);
+        self.inner.stderr(cfg);
+        self
+    }
+
+    /// The [`Command`] wrapped by this command.
+    pub fn as_std(&self) -> &Command {
+        &self.inner
+    }
+
+    /// Like [`Self::as_std`], but mutable, for configuration that this API does not offer.
+    ///
+    /// Note that [`Command::env_clear`] is not reflected in this command's [`Display`]
+    /// implementation when called this way; use [`Self::env_clear`] instead. Likewise, streams
+    /// configured this way may be overridden by methods that capture or connect them; use
+    /// [`Self::stdin`] and the like instead.
+    pub fn as_std_mut(&mut self) -> &mut Command {
+        &mut self.inner
+    }
+
+    /// Terminate the child process if it has not exited after `timeout`, according to
+    /// [`Self::termination`].
+    ///
+    /// This applies to all methods that execute this command. Time spent spawning the child
+    /// process and reading its output counts towards the timeout.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// How to stop the child process whenever it must be stopped early. Defaults to
+    /// [`TerminationPolicy::kill`].
+    pub fn termination(&mut self, policy: TerminationPolicy) -> &mut Self {
+        self.termination = policy;
+        self
+    }
+
+    /// Spawn child processes for this command with `executor`, rather than the default
+    /// [`executor::SystemExecutor`] (or that set with [`executor::with_default`]).
+    pub fn executor(&mut self, executor: Arc<dyn Executor>) -> &mut Self {
+        self.executor = Some(executor);
+        self
+    }
+
+    /// Log this command instead of executing it, as configured by `dry_run`. This takes precedence
+    /// over any [`Executor`].
+    pub fn dry_run(&mut self, dry_run: DryRun) -> &mut Self {
+        self.dry_run = Some(dry_run);
+        self
+    }
+
+    fn spawn_running(&mut self) -> Result<Running, SpawnError> {
+        self.spawn_with_stdio(None, None, None)
+    }
+
+    /// Spawns a child process with its streams as currently configured.
+    fn spawn_configured(&mut self) -> Result<Running, SpawnError> {
+        let invocation = EasyCommandInvocation::new(self);
+        let command_line = invocation.to_string();
+        let child = match DryRun::resolve(self.dry_run.as_ref()) {
+            Some(dry_run) => dry_run.skip(&command_line),
+            None => {
+                let executor = executor::resolve(self.executor.as_ref());
+                executor
+                    .spawn(&mut self.inner, &command_line)
+                    .map_err(|e| executor.classify_spawn_error(&self.inner, self.env_cleared, e))?
+            }
+        };
+        Ok(Running::new(
+            child,
+            invocation,
+            self.timeout,
+            self.termination,
+        ))
+    }
+
+    /// Like [`Self::spawn_running`], but overriding the configuration of any streams that are
+    /// specified, unless they were configured explicitly.
+    ///
+    /// Afterwards, overridden streams are reset to being inherited, which is the default for
+    /// [`Command::spawn`]. If this command has an [`Input`], it is written to `stdin`.
+    fn spawn_with_stdio(
+        &mut self,
+        stdin: Option<Stdio>,
+        stdout: Option<Stdio>,
+        stderr: Option<Stdio>,
+    ) -> Result<Running, SpawnError> {
+        let unset = |config: StdioConfig| config == StdioConfig::Unset;
+        let stdin = stdin.filter(|_| unset(self.stdio.stdin));
+        let stdout = stdout.filter(|_| unset(self.stdio.stdout));
+        let stderr = stderr.filter(|_| unset(self.stdio.stderr));
+        let reset_stdin = stdin.map(|stdin| self.inner.stdin(stdin)).is_some();
+        let reset_stdout = stdout.map(|stdout| self.inner.stdout(stdout)).is_some();
+        let reset_stderr = stderr.map(|stderr| self.inner.stderr(stderr)).is_some();
+        let spawned = self.spawn_configured();
+        if reset_stdin {
+            self.inner.stdin(Stdio::inherit());
+        }
+        if reset_stdout {
+            self.inner.stdout(Stdio::inherit());
+        }
+        if reset_stderr {
+            self.inner.stderr(Stdio::inherit());
+        }
+
+        let mut child = spawned?;
+        if let Some(input) = &mut self.input {
+            child.write_input(input.take());
+        }
+        Ok(child)
+    }
+
+    /// Like [`Self::spawn_running`], but with a null `stdin` and piped `stdout` and `stderr`, like
+    /// [`Command::output`].
+    fn spawn_piped(&mut self) -> Result<Running, SpawnError> {
+        self.spawn_with_stdio(
+            Some(Stdio::null()),
+            Some(Stdio::piped()),
+            Some(Stdio::piped()),
+        )
+    }
+
     fn spawn_and_wait_impl(&mut self) -> Result<ExitStatus, SpawnAndWaitErrorKind> {
         log::debug!("spawning child process with {self}…");
-
-        self.inner
-            .spawn()
-            .map_err(|source| SpawnAndWaitErrorKind::Spawn { source })
-            .and_then(|mut child| {
-                log::trace!("waiting for exit from {self}…");
-                let status = child
-                    .wait()
-                    .map_err(|source| SpawnAndWaitErrorKind::WaitForExitCode { source })?;
-                log::debug!("received exit code {:?} from {self}", status.code());
-                Ok(status)
-            })
+        let child = self
+            .spawn_running()
+            .map_err(|source| SpawnAndWaitErrorKind::Spawn { source })?;
+        child.spawn_and_wait()
     }
 
     /// Execute this command, returning its exit status.
@@ -76,15 +425,7 @@ impl EasyCommand {
     }
 
     fn run_impl(&mut self) -> Result<(), RunErrorKind> {
-        let status = self.spawn_and_wait_impl()?;
-
-        if status.success() {
-            Ok(())
-        } else {
-            Err(RunErrorKind::UnsuccessfulExitCode {
-                code: status.code(),
-            })
-        }
+        check_status(self.spawn_and_wait_impl()?)
     }
 
     /// Execute this command, returning an error if it did not return a successful exit code.
@@ -96,48 +437,284 @@ impl EasyCommand {
             .map_err(|source| ExecuteError::new(self, source))
     }
 
-    fn output_impl(&mut self) -> Result<Output, io::Error> {
+    fn output_impl(&mut self) -> Result<Output, OutputErrorKind> {
         log::debug!("getting output from {self}…");
-        let output = self.inner.output()?;
-        log::debug!("received exit code {:?} from {self}", output.status.code());
-        Ok(output)
+        let child = self
+            .spawn_piped()
+            .map_err(|source| OutputErrorKind::Spawn { source })?;
+        child.output()
     }
 
     /// Execute this command, capturing its output.
-    pub fn output(&mut self) -> Result<Output, ExecuteError<io::Error>> {
-        self.output_impl()
-            .map_err(|source| ExecuteError::new(self, source))
+    ///
+    /// Like [`Command::output`], `stdin` is null, and `stdout` and `stderr` are captured, except for
+    /// those streams configured otherwise, like with [`Self::stdout`]. Streams configured in
+    /// [`Self::new_with`] are only kept if detected, on a best-effort basis.
+    pub fn output(&mut self) -> Result<Output, ExecuteError<OutputErrorKind>> {
+        self.output_impl().map_err(|source| {
+            let stderr_tail = source.stderr_tail();
+            ExecuteError::new(self, source).with_output_tail(stderr_tail)
+        })
+    }
+
+    fn output_checked_impl(&mut self) -> Result<Output, OutputCheckedErrorKind> {
+        check_output(self.output_impl()?)
+    }
+
+    /// Execute this command, capturing its output, and returning an error if it did not return a
+    /// successful exit code.
+    ///
+    /// On failure, the returned error's [`Display`] implementation includes the last few lines of
+    /// the captured `stderr`.
+    pub fn output_checked(&mut self) -> Result<Output, ExecuteError<OutputCheckedErrorKind>> {
+        self.output_checked_impl().map_err(|source| {
+            let stderr_tail = source.stderr_tail();
+            ExecuteError::new(self, source).with_output_tail(stderr_tail)
+        })
+    }
+
+    fn output_string_impl(&mut self) -> Result<String, OutputStringErrorKind> {
+        let Output { stdout, .. } = self.output_checked_impl()?;
+        String::from_utf8(stdout).map_err(|source| OutputStringErrorKind::InvalidUtf8 { source })
+    }
+
+    /// Like [`Self::output_checked`], but returns `stdout` as a [`String`], returning an error if
+    /// it is not valid UTF-8.
+    pub fn output_string(&mut self) -> Result<String, ExecuteError<OutputStringErrorKind>> {
+        self.output_string_impl().map_err(|source| {
+            let stderr_tail = match &source {
+                OutputStringErrorKind::OutputChecked(source) => source.stderr_tail(),
+                OutputStringErrorKind::InvalidUtf8 { .. } => None,
+            };
+            ExecuteError::new(self, source).with_output_tail(stderr_tail)
+        })
+    }
+
+    /// Like [`Self::output_string`], but with leading and trailing whitespace removed.
+    pub fn output_string_trimmed(&mut self) -> Result<String, ExecuteError<OutputStringErrorKind>> {
+        self.output_string().map(|stdout| {
+            let trimmed = stdout.trim();
+            if trimmed.len() == stdout.len() {
+                stdout
+            } else {
+                trimmed.to_owned()
+            }
+        })
     }
 }
 
+impl Running {
+    fn spawn_and_wait(mut self) -> Result<ExitStatus, SpawnAndWaitErrorKind> {
+        log::trace!("waiting for exit from `{}`…", self.cmd());
+        let status = self.wait()?;
+        log::debug!(
+            "received exit code {:?} from `{}`",
+            status.code(),
+            self.cmd()
+        );
+        Ok(status)
+    }
+
+    fn output(self) -> Result<Output, OutputErrorKind> {
+        self.output_with(usize::MAX, |_stream, _data| ())
+    }
+
+    /// Like [`Self::output`], but keeping only the last `max_len` bytes of each stream, and handing
+    /// each chunk read to `on_chunk`, too.
+    fn output_with(
+        mut self,
+        max_len: usize,
+        mut on_chunk: impl FnMut(Stream, &[u8]),
+    ) -> Result<Output, OutputErrorKind> {
+        let (mut stdout, mut stderr) = (TailBuffer::new(max_len), TailBuffer::new(max_len));
+        let status = self.wait_with_output(|stream, data| {
+            match stream {
+                Stream::Stdout => stdout.push(data),
+                Stream::Stderr => stderr.push(data),
+            }
+            on_chunk(stream, data);
+        });
+        let (stdout, stderr) = (stdout.into_bytes(), stderr.into_bytes());
+        let status = match status {
+            Ok(status) => status,
+            Err(CollectError::ReadOutput(source)) => {
+                return Err(OutputErrorKind::ReadOutput { source })
+            }
+            Err(CollectError::Wait(WaitError::Wait(source))) => {
+                return Err(OutputErrorKind::WaitForExitCode { source })
+            }
+            Err(CollectError::Wait(WaitError::TimedOut { elapsed, stage })) => {
+                return Err(OutputErrorKind::TimedOut {
+                    elapsed,
+                    stage,
+                    stdout,
+                    stderr,
+                })
+            }
+            Err(CollectError::Wait(WaitError::WriteInput { status, source })) => {
+                return Err(OutputErrorKind::WriteInput {
+                    source,
+                    output: Output {
+                        status,
+                        stdout,
+                        stderr,
+                    },
+                })
+            }
+        };
+        log::debug!(
+            "received exit code {:?} from `{}`",
+            status.code(),
+            self.cmd()
+        );
+        Ok(Output {
+            status,
+            stdout,
+            stderr,
+        })
+    }
+}
+
+fn check_status(status: ExitStatus) -> Result<(), RunErrorKind> {
+    if status.success() {
+        Ok(())
+    } else {
+        let (signal, core_dumped) = Signal::of_exit(&status);
+        Err(RunErrorKind::UnsuccessfulExitCode {
+            code: status.code(),
+            signal,
+            core_dumped,
+        })
+    }
+}
+
+fn check_output(output: Output) -> Result<Output, OutputCheckedErrorKind> {
+    if output.status.success() {
+        Ok(output)
+    } else {
+        let (signal, core_dumped) = Signal::of_exit(&output.status);
+        Err(OutputCheckedErrorKind::UnsuccessfulExitCode {
+            code: output.status.code(),
+            signal,
+            core_dumped,
+            output,
+        })
+    }
+}
+
+/// Shows this command as it is displayed, with any secrets hidden.
 impl Debug for EasyCommand {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        Debug::fmt(&self.inner, f)
+        f.debug_struct("EasyCommand")
+            .field("cmd", &EasyCommandInvocation::new(self).shell_words)
+            .finish_non_exhaustive()
     }
 }
 
+/// Displays this command in shell syntax, surrounded by backticks, like `` `cargo build` ``. The
+/// alternate form (`{:#}`) omits the backticks, and can be parsed back with [`EasyCommand::parse`].
 impl Display for EasyCommand {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        let Self { inner } = self;
-        let prog = inner.get_program().to_string_lossy();
-        let args = inner.get_args().map(|a| a.to_string_lossy());
-        let shell_words = ::shell_words::join(once(prog).chain(args));
-        write!(f, "`{shell_words}`")
+        let invocation = EasyCommandInvocation::new(self);
+        if f.alternate() {
+            write!(f, "{invocation}")
+        } else {
+            write!(f, "`{invocation}`")
+        }
     }
 }
 
-#[derive(Debug)]
+impl FromStr for EasyCommand {
+    type Err = ParseCommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// An error encountered with [`EasyCommand::parse`].
+#[derive(Debug, thiserror::Error)]
+pub enum ParseCommandError {
+    #[error("failed to parse command line {command_line:?}")]
+    UnbalancedQuotes {
+        command_line: String,
+        source: ::shell_words::ParseError,
+    },
+    #[error("command line {command_line:?} has no program")]
+    MissingProgram { command_line: String },
+}
+
+#[derive(Clone, Debug)]
 struct EasyCommandInvocation {
-    shell_words: String,
+    shell_words: Box<str>,
 }
 
 impl EasyCommandInvocation {
     fn new(cmd: &EasyCommand) -> Self {
-        let EasyCommand { inner } = cmd;
-        let prog = inner.get_program().to_string_lossy();
-        let args = inner.get_args().map(|a| a.to_string_lossy());
-        let shell_words = ::shell_words::join(once(prog).chain(args));
-        Self { shell_words }
+        Self::render(&cmd.inner, cmd.env_cleared, &cmd.secrets)
+    }
+
+    /// Renders `inner` as a line that can be copied into a shell, including its working directory
+    /// and environment overrides, like `cd /x && RUSTFLAGS=... env -u FOO cargo build`. If its
+    /// environment was cleared, that is rendered like `env -i RUSTFLAGS=... cargo build`. Any
+    /// `secrets` are rendered as `***`.
+    fn render(inner: &Command, env_cleared: bool, secrets: &Secrets) -> Self {
+        let mut shell_words = String::new();
+
+        if let Some(dir) = inner.get_current_dir() {
+            let dir = dir.to_string_lossy();
+            shell_words += &format!("cd {} && ", ::shell_words::quote(&dir));
+        }
+
+        if env_cleared {
+            shell_words += "env -i ";
+        }
+        let mut removed_envs = Vec::new();
+        for (key, value) in inner.get_envs() {
+            match value {
+                Some(value) => {
+                    let value = secrets.quote_env_value(key, &value.to_string_lossy());
+                    shell_words += &format!("{}={value} ", key.to_string_lossy());
+                }
+                None if env_cleared => (),
+                None => removed_envs.push(key.to_string_lossy()),
+            }
+        }
+        if !removed_envs.is_empty() {
+            shell_words += "env ";
+            for key in removed_envs {
+                shell_words += &format!("-u {} ", ::shell_words::quote(&key));
+            }
+        }
+
+        let prog = secrets.quote("", &inner.get_program().to_string_lossy());
+        let args = inner
+            .get_args()
+            .enumerate()
+            .map(|(idx, arg)| secrets.quote_arg(idx, &arg.to_string_lossy()));
+        shell_words += &once(prog).chain(args).collect::<Vec<_>>().join(" ");
+        Self {
+            shell_words: shell_words.into(),
+        }
+    }
+
+    fn pipeline(stages: &[EasyCommand]) -> Self {
+        let shell_words = stages
+            .iter()
+            .map(|stage| {
+                let Self { shell_words } = Self::new(stage);
+                if stage.inner.get_current_dir().is_some() {
+                    // Otherwise, the `cd` would apply to the rest of the pipeline, too.
+                    format!("({shell_words})")
+                } else {
+                    shell_words.into()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        Self {
+            shell_words: shell_words.into(),
+        }
     }
 }
 
@@ -148,30 +725,153 @@ impl Display for EasyCommandInvocation {
     }
 }
 
+/// The last few lines of a child process' output, usually its `stderr`, kept for error reporting.
+#[derive(Debug)]
+struct OutputTail {
+    /// What output this is, like `stderr`.
+    label: &'static str,
+    lines: Vec<String>,
+    truncated: bool,
+}
+
+impl OutputTail {
+    const MAX_LINES: usize = 20;
+    const MAX_BYTES: usize = 4096;
+
+    fn stderr(stderr: &[u8]) -> Option<Self> {
+        Self::new("stderr", stderr)
+    }
+
+    fn new(label: &'static str, output: &[u8]) -> Option<Self> {
+        let output = String::from_utf8_lossy(output);
+        let output = output.trim_end();
+        if output.is_empty() {
+            return None;
+        }
+
+        let mut start = output.len().saturating_sub(Self::MAX_BYTES);
+        while !output.is_char_boundary(start) {
+            start += 1;
+        }
+        let mut lines = output[start..]
+            .lines()
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>();
+        let mut truncated = start > 0;
+        if start > 0 {
+            // The first line is most likely cut off in the middle, so drop it, unless it is all
+            // there is, in which case mark where it was cut off.
+            if lines.len() > 1 {
+                lines.remove(0);
+            } else {
+                lines[0].insert(0, '…');
+            }
+        }
+        if lines.len() > Self::MAX_LINES {
+            lines.drain(..lines.len() - Self::MAX_LINES);
+            truncated = true;
+        }
+
+        Some(Self {
+            label,
+            lines,
+            truncated,
+        })
+    }
+}
+
+impl Display for OutputTail {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Self {
+            label,
+            lines,
+            truncated,
+        } = self;
+        write!(f, "; {label}")?;
+        match (*truncated, lines.len()) {
+            (false, _) => {}
+            (true, 1) => write!(f, " (last line)")?,
+            (true, len) => write!(f, " (last {len} lines)")?,
+        }
+        write!(f, ":")?;
+        for line in lines {
+            write!(f, "\n    {line}")?;
+        }
+        Ok(())
+    }
+}
+
 /// An error returned by [`EasyCommand`]'s methods.
 #[derive(Debug, thiserror::Error)]
-#[error("failed to execute {cmd}")]
+#[error("failed to execute {cmd}{}", DisplayOpt(output_tail.as_deref()))]
 pub struct ExecuteError<E> {
     cmd: EasyCommandInvocation,
+    output_tail: Option<Box<OutputTail>>,
     pub source: E,
 }
 
 impl<E> ExecuteError<E> {
     fn new(cmd: &EasyCommand, source: E) -> Self {
+        Self::for_invocation(EasyCommandInvocation::new(cmd), source)
+    }
+
+    fn for_invocation(cmd: EasyCommandInvocation, source: E) -> Self {
         Self {
-            cmd: EasyCommandInvocation::new(cmd),
+            cmd,
+            output_tail: None,
             source,
         }
     }
+
+    fn with_output_tail(self, output_tail: Option<OutputTail>) -> Self {
+        Self {
+            output_tail: output_tail.map(Box::new),
+            ..self
+        }
+    }
+}
+
+/// Displays nothing for [`None`], and the contained value otherwise.
+struct DisplayOpt<T>(Option<T>);
+
+impl<T> Display for DisplayOpt<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(t) => Display::fmt(t, f),
+            None => Ok(()),
+        }
+    }
 }
 
 /// The specific error case encountered with [`EasyCommand::spawn_and_wait`].
 #[derive(Debug, thiserror::Error)]
 pub enum SpawnAndWaitErrorKind {
     #[error("failed to spawn")]
-    Spawn { source: io::Error },
+    Spawn { source: SpawnError },
     #[error("failed to wait for exit code")]
     WaitForExitCode { source: io::Error },
+    /// The child process was stopped after running for longer than [`EasyCommand::timeout`].
+    #[error("timed out after {elapsed:?} ({stage})")]
+    TimedOut {
+        elapsed: Duration,
+        stage: TerminationStage,
+    },
+    /// The child process exited successfully, but its [`Input`] could not be written.
+    #[error("failed to write input")]
+    WriteInput { source: WriteInputError },
+}
+
+impl From<WaitError> for SpawnAndWaitErrorKind {
+    fn from(e: WaitError) -> Self {
+        match e {
+            WaitError::Wait(source) => Self::WaitForExitCode { source },
+            WaitError::TimedOut { elapsed, stage } => Self::TimedOut { elapsed, stage },
+            WaitError::WriteInput { status: _, source } => Self::WriteInput { source },
+        }
+    }
 }
 
 /// The specific error case encountered with a [`EasyCommand::run`].
@@ -179,6 +879,363 @@ pub enum SpawnAndWaitErrorKind {
 pub enum RunErrorKind {
     #[error(transparent)]
     SpawnAndWait(#[from] SpawnAndWaitErrorKind),
-    #[error("returned exit code {code:?}")]
-    UnsuccessfulExitCode { code: Option<i32> },
+    /// The child process exited unsuccessfully, either with a non-zero exit `code` or by being
+    /// killed by a `signal`.
+    #[error("{}", DisplayExit { code: *code, signal: *signal, core_dumped: *core_dumped })]
+    UnsuccessfulExitCode {
+        code: Option<i32>,
+        signal: Option<Signal>,
+        core_dumped: bool,
+    },
+}
+
+/// Describes how a child process exited unsuccessfully.
+struct DisplayExit {
+    code: Option<i32>,
+    signal: Option<Signal>,
+    core_dumped: bool,
+}
+
+impl Display for DisplayExit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Self {
+            code,
+            signal,
+            core_dumped,
+        } = self;
+        match (code, signal) {
+            (Some(code), _) => write!(f, "returned exit code {code}")?,
+            (None, Some(signal)) => write!(f, "killed by {signal}")?,
+            (None, None) => write!(f, "exited unsuccessfully without an exit code")?,
+        }
+        if *core_dumped {
+            write!(f, " (core dumped)")?;
+        }
+        Ok(())
+    }
+}
+
+/// The specific error case encountered with [`EasyCommand::output`].
+#[derive(Debug, thiserror::Error)]
+pub enum OutputErrorKind {
+    #[error("failed to spawn")]
+    Spawn { source: SpawnError },
+    #[error("failed to read output")]
+    ReadOutput { source: io::Error },
+    #[error("failed to wait for exit code")]
+    WaitForExitCode { source: io::Error },
+    /// The child process was stopped after running for longer than [`EasyCommand::timeout`].
+    /// `stdout` and `stderr` hold whatever output was read before then.
+    #[error("timed out after {elapsed:?} ({stage})")]
+    TimedOut {
+        elapsed: Duration,
+        stage: TerminationStage,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+    },
+    /// The child process exited successfully, but its [`Input`] could not be written. `output`
+    /// holds all of its output.
+    #[error("failed to write input")]
+    WriteInput {
+        source: WriteInputError,
+        output: Output,
+    },
+}
+
+impl OutputErrorKind {
+    fn stderr_tail(&self) -> Option<OutputTail> {
+        match self {
+            Self::TimedOut { stderr, .. } => OutputTail::stderr(stderr),
+            Self::WriteInput { output, .. } => OutputTail::stderr(&output.stderr),
+            Self::Spawn { .. } | Self::ReadOutput { .. } | Self::WaitForExitCode { .. } => None,
+        }
+    }
+}
+
+/// The specific error case encountered with [`EasyCommand::output_checked`].
+#[derive(Debug, thiserror::Error)]
+pub enum OutputCheckedErrorKind {
+    #[error(transparent)]
+    Output(#[from] OutputErrorKind),
+    /// Like [`RunErrorKind::UnsuccessfulExitCode`], but with the captured `output`.
+    #[error("{}", DisplayExit { code: *code, signal: *signal, core_dumped: *core_dumped })]
+    UnsuccessfulExitCode {
+        code: Option<i32>,
+        signal: Option<Signal>,
+        core_dumped: bool,
+        output: Output,
+    },
+}
+
+impl OutputCheckedErrorKind {
+    fn stderr_tail(&self) -> Option<OutputTail> {
+        match self {
+            Self::Output(source) => source.stderr_tail(),
+            Self::UnsuccessfulExitCode { output, .. } => OutputTail::stderr(&output.stderr),
+        }
+    }
+}
+
+/// The specific error case encountered with [`EasyCommand::output_string`] and
+/// [`EasyCommand::output_string_trimmed`].
+#[derive(Debug, thiserror::Error)]
+pub enum OutputStringErrorKind {
+    #[error(transparent)]
+    OutputChecked(#[from] OutputCheckedErrorKind),
+    #[error("`stdout` was not valid UTF-8")]
+    InvalidUtf8 { source: FromUtf8Error },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tail(output: &[u8]) -> String {
+        OutputTail::stderr(output).map_or_else(String::new, |tail| tail.to_string())
+    }
+
+    #[test]
+    fn output_tail_is_empty_for_blank_output() {
+        assert_eq!(tail(b""), "");
+        assert_eq!(tail(b" \n\n"), "");
+    }
+
+    #[test]
+    fn output_tail_shows_short_output_in_full() {
+        assert_eq!(tail(b"a\nb\n"), "; stderr:\n    a\n    b");
+    }
+
+    #[test]
+    fn output_tail_keeps_last_lines() {
+        let output = (1..=30).map(|i| format!("{i}\n")).collect::<String>();
+        let expected = (11..=30).map(|i| format!("\n    {i}")).collect::<String>();
+        assert_eq!(
+            tail(output.as_bytes()),
+            format!("; stderr (last 20 lines):{expected}")
+        );
+    }
+
+    #[test]
+    fn output_tail_drops_line_cut_off_by_byte_limit() {
+        let output = format!("{}\nlast\n", "x".repeat(10_000));
+        assert_eq!(tail(output.as_bytes()), "; stderr (last line):\n    last");
+    }
+
+    #[test]
+    fn output_tail_keeps_single_line_cut_off_by_byte_limit() {
+        let output = "x".repeat(10_000);
+        let expected = format!("; stderr (last line):\n    …{}", "x".repeat(4096));
+        assert_eq!(tail(output.as_bytes()), expected);
+    }
+
+    fn parse_round_trip(command_line: &str) -> EasyCommand {
+        let cmd = EasyCommand::parse(command_line).unwrap();
+        let reparsed = EasyCommand::parse(&format!("{cmd:#}")).unwrap();
+        assert_eq!(reparsed.to_spec().unwrap(), cmd.to_spec().unwrap());
+        cmd
+    }
+
+    #[test]
+    fn parse_round_trips_quoting() {
+        let cmd = parse_round_trip(r#"cargo test --features "a b" '' "it's" 'x"y' a\ b"#);
+        let spec = cmd.to_spec().unwrap();
+        assert_eq!(spec.program, "cargo");
+        assert_eq!(
+            spec.args,
+            ["test", "--features", "a b", "", "it's", "x\"y", "a b"]
+        );
+    }
+
+    #[test]
+    fn parse_round_trips_env() {
+        let cmd = parse_round_trip("RUSTFLAGS='-D warnings' EMPTY= cargo build X=y");
+        let spec = cmd.to_spec().unwrap();
+        assert_eq!(
+            spec.env,
+            std::collections::BTreeMap::from([
+                ("EMPTY".to_owned(), Some(String::new())),
+                ("RUSTFLAGS".to_owned(), Some("-D warnings".to_owned())),
+            ])
+        );
+        assert_eq!(spec.program, "cargo");
+        assert_eq!(spec.args, ["build", "X=y"]);
+    }
+
+    #[test]
+    fn parse_skips_line_continuations() {
+        let spec = parse_round_trip("A=1 \\\n B=2 prog \\\n arg")
+            .to_spec()
+            .unwrap();
+        assert_eq!(
+            spec.env.keys().collect::<Vec<_>>(),
+            [&"A".to_owned(), &"B".to_owned()]
+        );
+        assert_eq!(spec.program, "prog");
+        assert_eq!(spec.args, ["arg"]);
+    }
+
+    #[test]
+    fn parse_does_not_take_quoted_words_for_env() {
+        for command_line in ["'A=b' prog", "\"A=b\" prog", "A\\=b prog", "'A'=b prog"] {
+            let spec = parse_round_trip(command_line).to_spec().unwrap();
+            assert_eq!(spec.program, "A=b", "{command_line}");
+            assert!(spec.env.is_empty(), "{command_line}");
+        }
+        assert_eq!(EasyCommand::new("A=b").to_string(), "`'A=b'`");
+    }
+
+    #[test]
+    fn parse_rejects_invalid_command_lines() {
+        assert!(matches!(
+            EasyCommand::parse("cargo 'test"),
+            Err(ParseCommandError::UnbalancedQuotes { .. })
+        ));
+        assert!(matches!(
+            EasyCommand::parse("A=b "),
+            Err(ParseCommandError::MissingProgram { .. })
+        ));
+    }
+
+    fn secret_cmd() -> EasyCommand {
+        let mut cmd = EasyCommand::new("curl");
+        cmd.secret_env("TOKEN", "abc").secret_arg("hunter2");
+        cmd
+    }
+
+    #[test]
+    fn display_hides_secrets() {
+        assert_eq!(format!("{:#}", secret_cmd()), "TOKEN=*** curl ***");
+    }
+
+    #[test]
+    fn debug_hides_secrets() {
+        let debug = format!("{:?}", secret_cmd());
+        assert!(
+            !debug.contains("abc") && !debug.contains("hunter2"),
+            "{debug}"
+        );
+        let debug = format!("{:?}", secret_cmd().pipe(EasyCommand::new("cat")));
+        assert!(
+            !debug.contains("abc") && !debug.contains("hunter2"),
+            "{debug}"
+        );
+    }
+
+    #[test]
+    fn executors_see_secrets_hidden_in_command_lines() {
+        let executor = Arc::new(executor::FakeExecutor::new());
+        let mut cmd = secret_cmd();
+        cmd.executor(executor.clone());
+        let e = cmd.run().unwrap_err();
+        assert_eq!(executor.command_lines(), ["TOKEN=*** curl ***"]);
+        let chain = format!("{e} {:?}", e.source);
+        assert!(
+            !chain.contains("abc") && !chain.contains("hunter2"),
+            "{chain}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_captures_unset_streams() {
+        let mut cmd = EasyCommand::simple("echo", ["x"]);
+        assert_eq!(cmd.output().unwrap().stdout, b"x\n");
+        assert_eq!(cmd.output().unwrap().stdout, b"x\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_respects_configured_streams() {
+        let mut cmd = EasyCommand::new_with("echo", |cmd| cmd.arg("x").stdout(Stdio::null()));
+        assert_eq!(cmd.output().unwrap().stdout, b"");
+        assert!(cmd.to_spec().is_err());
+
+        let mut cmd = EasyCommand::simple("echo", ["x"]);
+        cmd.stdout(Stdio::null());
+        assert_eq!(cmd.output().unwrap().stdout, b"");
+        assert_eq!(cmd.output().unwrap().stdout, b"");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_allows_input_not_read_by_default() {
+        let mut cmd = EasyCommand::simple("head", ["-c1"]);
+        cmd.input(vec![b'x'; 1 << 20]);
+        assert_eq!(cmd.output().unwrap().stdout, b"x");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_keeps_output_when_input_required_but_not_read() {
+        let mut cmd = EasyCommand::simple("head", ["-c1"]);
+        cmd.input(Input::bytes(vec![b'x'; 1 << 20]).require_all_read());
+        match cmd.output().unwrap_err().source {
+            OutputErrorKind::WriteInput {
+                source: WriteInputError::BrokenPipe,
+                output,
+            } => {
+                assert!(output.status.success());
+                assert_eq!(output.stdout, b"x");
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn child_terminates_according_to_policy() {
+        let mut cmd = EasyCommand::simple("sleep", ["10"]);
+        cmd.termination(TerminationPolicy::graceful(Duration::from_secs(5)));
+        let mut child = cmd.spawn().unwrap();
+        assert_eq!(child.terminate(), TerminationStage::Signal(Signal::TERM));
+        assert!(!child.wait().unwrap().success());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn child_keeps_reporting_timeout() {
+        let mut cmd = EasyCommand::simple("sleep", ["10"]);
+        cmd.timeout(Duration::from_millis(10));
+        let mut child = cmd.spawn().unwrap();
+        let timed_out = |e: ExecuteError<SpawnAndWaitErrorKind>| {
+            matches!(e.source, SpawnAndWaitErrorKind::TimedOut { .. })
+        };
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(child.try_wait().is_err_and(timed_out));
+        assert!(child.try_wait().is_err_and(timed_out));
+        assert!(child.wait().is_err_and(timed_out));
+    }
+
+    #[test]
+    fn unsuccessful_exit_shows_exit_code() {
+        let executor = Arc::new(executor::FakeExecutor::new());
+        executor.on_program("check", executor::FakeResponse::exit_code(3));
+        let mut cmd = EasyCommand::new("check");
+        cmd.executor(executor);
+        let e = cmd.run().unwrap_err();
+        assert_eq!(e.source.to_string(), "returned exit code 3");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unsuccessful_exit_shows_killing_signal() {
+        let executor = Arc::new(executor::FakeExecutor::new());
+        executor.on_program("worker", executor::FakeResponse::signal(Signal::KILL));
+        let mut cmd = EasyCommand::new("worker");
+        cmd.executor(executor);
+        let e = cmd.output_checked().unwrap_err();
+        assert_eq!(e.source.to_string(), "killed by signal 9 (SIGKILL)");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unsuccessful_exit_shows_core_dump() {
+        use std::os::unix::process::ExitStatusExt;
+
+        // The flag that `wait` sets in the status of a process that dumped core.
+        const CORE_DUMPED: i32 = 0x80;
+        let status = ExitStatus::from_raw(Signal::SEGV.number() | CORE_DUMPED);
+        let e = check_status(status).unwrap_err();
+        assert_eq!(e.to_string(), "killed by signal 11 (SIGSEGV) (core dumped)");
+    }
 }
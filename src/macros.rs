@@ -0,0 +1,71 @@
+/// Builds an [`EasyCommand`](crate::EasyCommand) from a program and a comma-separated list of
+/// arguments, like `cmd!("git", "commit", "-m", message)`.
+///
+/// Each argument is one of:
+///
+/// * An expression, like `"-m"` or `message`, which becomes a single argument, even if it contains
+///   spaces or quotes.
+/// * `..iterable`, like `..&features`, which becomes one argument per item of `iterable`.
+/// * `[if condition] argument` or `[if condition] ..iterable`, which is included only if
+///   `condition` is `true`, like `[if amend] "--amend"`.
+///
+/// All values must implement `AsRef<OsStr>`, as with [`Command::arg`] and [`Command::args`]. No
+/// shell is involved, so there is no risk of arguments being interpreted as shell syntax.
+///
+/// ```
+/// # use ezcmd::cmd;
+/// let (amend, verbose, message) = (true, false, "fix: typo");
+/// let paths = ["README.md", "src/lib.rs"];
+/// let cmd = cmd!(
+///     "git",
+///     "commit",
+///     [if amend] "--amend",
+///     if verbose { "-v" } else { "-q" },
+///     "-m",
+///     message,
+///     "--",
+///     ..&paths,
+/// );
+/// assert_eq!(
+///     cmd.to_string(),
+///     "`git commit --amend -q -m 'fix: typo' -- README.md src/lib.rs`"
+/// );
+/// ```
+///
+/// [`Command::arg`]: std::process::Command::arg
+/// [`Command::args`]: std::process::Command::args
+#[macro_export]
+macro_rules! cmd {
+    ($program:expr $(, $($args:tt)*)?) => {
+        $crate::EasyCommand::new_with($program, |cmd| {
+            $($crate::__cmd_args!(cmd; $($args)*);)?
+            cmd
+        })
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __cmd_args {
+    ($cmd:ident;) => {};
+    ($cmd:ident; [if $condition:expr] ..$args:expr $(, $($rest:tt)*)?) => {
+        if $condition {
+            $cmd.args($args);
+        }
+        $crate::__cmd_args!($cmd; $($($rest)*)?);
+    };
+    ($cmd:ident; [if $condition:expr] $arg:expr $(, $($rest:tt)*)?) => {
+        if $condition {
+            $cmd.arg($arg);
+        }
+        $crate::__cmd_args!($cmd; $($($rest)*)?);
+    };
+    ($cmd:ident; ..$args:expr $(, $($rest:tt)*)?) => {
+        $cmd.args($args);
+        $crate::__cmd_args!($cmd; $($($rest)*)?);
+    };
+    ($cmd:ident; $arg:expr $(, $($rest:tt)*)?) => {
+        $cmd.arg($arg);
+        $crate::__cmd_args!($cmd; $($($rest)*)?);
+    };
+}
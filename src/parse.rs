@@ -0,0 +1,197 @@
+//! Splitting command lines in shell syntax into words, for [`EasyCommand::parse`].
+//!
+//! [`EasyCommand::parse`]: crate::EasyCommand::parse
+
+use ::shell_words::ParseError;
+
+/// A word of a command line, with its quoting removed.
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) struct Word {
+    text: String,
+    /// How many leading bytes of `text` were neither quoted nor escaped.
+    unquoted_len: usize,
+}
+
+impl Word {
+    /// The name and value of this word, if it is a variable assignment, like `FOO=bar`, as a
+    /// shell would treat it before a program. As in a shell, the name and `=` must be unquoted.
+    pub fn env_assignment(&self) -> Option<(&str, &str)> {
+        let (key, value) = self.text.split_once('=')?;
+        let mut chars = key.chars();
+        let is_name = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        (is_name && key.len() < self.unquoted_len).then_some((key, value))
+    }
+
+    pub fn into_text(self) -> String {
+        self.text
+    }
+}
+
+/// Splits `command_line` into words exactly as [`shell_words::split`] does, while keeping track
+/// of which parts of each word were quoted.
+pub(crate) fn split(command_line: &str) -> Result<Vec<Word>, ParseError> {
+    #[derive(Clone, Copy)]
+    enum State {
+        Delimiter,
+        Backslash,
+        Unquoted,
+        UnquotedBackslash,
+        SingleQuoted,
+        DoubleQuoted,
+        DoubleQuotedBackslash,
+        Comment,
+    }
+    use State::*;
+
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut unquoted_len = None;
+    let mut finish = |word: &mut String, unquoted_len: &mut Option<usize>| {
+        let text = std::mem::take(word);
+        words.push(Word {
+            unquoted_len: unquoted_len.take().unwrap_or(text.len()),
+            text,
+        });
+    };
+    // Called whenever quoting or escaping starts, to record how much of the word preceded it.
+    let quote = |word: &String, unquoted_len: &mut Option<usize>| {
+        unquoted_len.get_or_insert(word.len());
+    };
+
+    let mut chars = command_line.chars();
+    let mut state = Delimiter;
+    loop {
+        let c = chars.next();
+        state = match (state, c) {
+            (Delimiter | Comment, None) => break,
+            (Delimiter, Some('\'')) => {
+                quote(&word, &mut unquoted_len);
+                SingleQuoted
+            }
+            (Delimiter, Some('"')) => {
+                quote(&word, &mut unquoted_len);
+                DoubleQuoted
+            }
+            (Delimiter, Some('\\')) => Backslash,
+            (Delimiter, Some('\t' | ' ' | '\n')) => Delimiter,
+            (Delimiter, Some('#')) => Comment,
+            (Backslash | UnquotedBackslash, None) => {
+                word.push('\\');
+                finish(&mut word, &mut unquoted_len);
+                break;
+            }
+            // A line continuation, which is not part of any word.
+            (Backslash, Some('\n')) => Delimiter,
+            (UnquotedBackslash, Some('\n')) => Unquoted,
+            (Backslash | UnquotedBackslash, Some(c)) => {
+                quote(&word, &mut unquoted_len);
+                word.push(c);
+                Unquoted
+            }
+            (Unquoted, None) => {
+                finish(&mut word, &mut unquoted_len);
+                break;
+            }
+            (Unquoted, Some('\'')) => {
+                quote(&word, &mut unquoted_len);
+                SingleQuoted
+            }
+            (Unquoted, Some('"')) => {
+                quote(&word, &mut unquoted_len);
+                DoubleQuoted
+            }
+            (Unquoted, Some('\\')) => UnquotedBackslash,
+            (Unquoted, Some('\t' | ' ' | '\n')) => {
+                finish(&mut word, &mut unquoted_len);
+                Delimiter
+            }
+            (Delimiter | Unquoted, Some(c)) => {
+                word.push(c);
+                Unquoted
+            }
+            (SingleQuoted | DoubleQuoted | DoubleQuotedBackslash, None) => return Err(ParseError),
+            (SingleQuoted, Some('\'')) | (DoubleQuoted, Some('"')) => Unquoted,
+            (SingleQuoted, Some(c)) => {
+                word.push(c);
+                SingleQuoted
+            }
+            (DoubleQuoted, Some('\\')) => DoubleQuotedBackslash,
+            (DoubleQuoted, Some(c)) => {
+                word.push(c);
+                DoubleQuoted
+            }
+            (DoubleQuotedBackslash, Some('\n')) => DoubleQuoted,
+            (DoubleQuotedBackslash, Some(c @ ('$' | '`' | '"' | '\\'))) => {
+                word.push(c);
+                DoubleQuoted
+            }
+            (DoubleQuotedBackslash, Some(c)) => {
+                word.push('\\');
+                word.push(c);
+                DoubleQuoted
+            }
+            (Comment, Some('\n')) => Delimiter,
+            (Comment, Some(_)) => Comment,
+        };
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(command_line: &str) -> Vec<String> {
+        split(command_line)
+            .unwrap()
+            .into_iter()
+            .map(Word::into_text)
+            .collect()
+    }
+
+    #[test]
+    fn split_matches_shell_words() {
+        for command_line in [
+            "",
+            "a b\tc\nd",
+            r#"a 'b c' "d e" f\ g"#,
+            r#"'' "" a''b"#,
+            r#""a\$b\"c\\d\e" 'a\b'"#,
+            "a \\\nb c\\\nd \"e\\\nf\"",
+            "a # b c\nd #e",
+            "a b#c",
+            "trailing\\",
+        ] {
+            assert_eq!(
+                texts(command_line),
+                ::shell_words::split(command_line).unwrap(),
+                "{command_line:?}"
+            );
+        }
+        for command_line in ["'a", "\"a", "\"a\\"] {
+            assert!(split(command_line).is_err(), "{command_line:?}");
+            assert!(::shell_words::split(command_line).is_err());
+        }
+    }
+
+    #[test]
+    fn env_assignment_requires_unquoted_name_and_equals() {
+        let words = split(r#"A=b _1='c d' E="" 'F=g' "H"=i J\=k L'=m' N\ O=p =q"#).unwrap();
+        let assignments = words.iter().map(Word::env_assignment).collect::<Vec<_>>();
+        let expected = [
+            Some(("A", "b")),
+            Some(("_1", "c d")),
+            Some(("E", "")),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ];
+        assert_eq!(assignments, expected);
+    }
+}
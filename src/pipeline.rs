@@ -0,0 +1,399 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    process::{ExitStatus, Output, Stdio},
+    thread,
+};
+
+use crate::{
+    child::{self, CollectError, Running, Stream, WaitError},
+    EasyCommand, EasyCommandInvocation, ExecuteError, OutputTail, RunErrorKind, Signal,
+    SpawnAndWaitErrorKind,
+};
+
+/// Several [`EasyCommand`]s, each with its `stdout` connected to the `stdin` of the next, like
+/// `a | b | c` in a shell.
+///
+/// Like in a shell, only the exit status of the last command counts by default. Use
+/// [`Self::pipefail`] to fail if any command fails.
+#[derive(Debug)]
+pub struct Pipeline {
+    stages: Vec<EasyCommand>,
+    pipefail: bool,
+}
+
+impl EasyCommand {
+    /// Create a [`Pipeline`] that connects this command's `stdout` to `next`'s `stdin`.
+    ///
+    /// Streams configured otherwise, like with [`Self::stdout`], are left as they are. Streams
+    /// configured in [`Self::new_with`] are only kept if detected, on a best-effort basis.
+    pub fn pipe(self, next: EasyCommand) -> Pipeline {
+        let mut pipeline = Pipeline::new(self);
+        pipeline.pipe(next);
+        pipeline
+    }
+}
+
+impl Pipeline {
+    /// Create a pipeline consisting of only `first`.
+    pub fn new(first: EasyCommand) -> Self {
+        Self {
+            stages: vec![first],
+            pipefail: false,
+        }
+    }
+
+    /// Connect the `stdout` of the current last command to `next`'s `stdin`.
+    pub fn pipe(&mut self, next: EasyCommand) -> &mut Self {
+        self.stages.push(next);
+        self
+    }
+
+    /// Whether to fail if any command in this pipeline fails, like `set -o pipefail` in Bash.
+    ///
+    /// When enabled, the last command that returned an unsuccessful exit code is reported. Defaults
+    /// to `false`.
+    pub fn pipefail(&mut self, pipefail: bool) -> &mut Self {
+        self.pipefail = pipefail;
+        self
+    }
+
+    /// Spawns all stages, connecting each to the next. If `capture` is set, the first stage gets a
+    /// null `stdin`, and the last stage gets piped `stdout` and `stderr`.
+    ///
+    /// If any stage fails to spawn, stages already spawned are terminated.
+    fn spawn(&mut self, capture: bool) -> Result<Vec<Running>, PipelineErrorKind> {
+        log::debug!("spawning pipeline {self}…");
+        let last = self.stages.len() - 1;
+        let mut children = Vec::<Running>::with_capacity(self.stages.len());
+        for (idx, stage) in self.stages.iter_mut().enumerate() {
+            let mut copy_from_prev = None;
+            let stdin = match children.last_mut() {
+                Some(prev) => match prev.take_stdout_as_stdio() {
+                    Some(stdio) => Some(stdio),
+                    None => {
+                        copy_from_prev = prev.take_stdout();
+                        Some(Stdio::piped())
+                    }
+                },
+                None => capture.then(Stdio::null),
+            };
+            let stdout = (idx != last || capture).then(Stdio::piped);
+            let stderr = (idx == last && capture).then(Stdio::piped);
+
+            log::debug!("spawning child process with {stage}…");
+            match stage.spawn_with_stdio(stdin, stdout, stderr) {
+                Ok(mut child) => {
+                    if let (Some(mut reader), Some(mut writer)) =
+                        (copy_from_prev, child.take_stdin())
+                    {
+                        thread::spawn(move || io::copy(&mut reader, &mut writer));
+                    }
+                    children.push(child);
+                }
+                Err(source) => {
+                    for child in &mut children {
+                        child.terminate();
+                    }
+                    return Err(PipelineErrorKind::new(
+                        idx,
+                        stage,
+                        RunErrorKind::from(SpawnAndWaitErrorKind::Spawn { source }).into(),
+                    ));
+                }
+            }
+        }
+        Ok(children)
+    }
+
+    /// Picks the error to report from the results of all stages, if any: the first stage that
+    /// could not be waited upon, or else the stage whose exit status should be reported, if it was
+    /// unsuccessful.
+    fn check(
+        &self,
+        results: Vec<Result<ExitStatus, PipelineStageErrorKind>>,
+    ) -> Result<ExitStatus, PipelineErrorKind> {
+        let mut statuses = Vec::with_capacity(results.len());
+        for (idx, result) in results.into_iter().enumerate() {
+            match result {
+                Ok(status) => statuses.push(status),
+                Err(source) => return Err(PipelineErrorKind::new(idx, &self.stages[idx], source)),
+            }
+        }
+
+        let last_status = *statuses.last().unwrap();
+        let reported = if self.pipefail {
+            statuses.iter().rposition(|status| !status.success())
+        } else {
+            Some(statuses.len() - 1).filter(|_| !last_status.success())
+        };
+        match reported {
+            None => Ok(last_status),
+            Some(idx) => {
+                let status = statuses[idx];
+                let (signal, core_dumped) = Signal::of_exit(&status);
+                let source = RunErrorKind::UnsuccessfulExitCode {
+                    code: status.code(),
+                    signal,
+                    core_dumped,
+                };
+                Err(PipelineErrorKind::new(
+                    idx,
+                    &self.stages[idx],
+                    source.into(),
+                ))
+            }
+        }
+    }
+
+    fn run_impl(&mut self) -> Result<(), PipelineErrorKind> {
+        let mut children = self.spawn(false)?;
+        log::trace!("waiting for exit from {self}…");
+        let results = child::wait_all(&mut children)
+            .into_iter()
+            .map(|result| result.map_err(PipelineStageErrorKind::from))
+            .collect();
+        self.check(results)?;
+        log::debug!("pipeline {self} succeeded");
+        Ok(())
+    }
+
+    /// Execute this pipeline, returning an error if it did not succeed.
+    ///
+    /// The first command's `stdin`, the last command's `stdout`, and all commands' `stderr` are
+    /// inherited from the parent.
+    pub fn run(&mut self) -> Result<(), ExecuteError<PipelineErrorKind>> {
+        self.run_impl()
+            .map_err(|source| ExecuteError::for_invocation(self.invocation(), source))
+    }
+
+    fn output_impl(&mut self) -> Result<Output, (PipelineErrorKind, Vec<u8>)> {
+        let mut children = self.spawn(true).map_err(|e| (e, Vec::new()))?;
+        let mut last = children.pop().unwrap();
+        let upstream = thread::spawn(move || child::wait_all(&mut children));
+
+        log::trace!("waiting for output from {self}…");
+        let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+        let last_result = last
+            .wait_with_output(|stream, data| match stream {
+                Stream::Stdout => stdout.extend_from_slice(data),
+                Stream::Stderr => stderr.extend_from_slice(data),
+            })
+            .map_err(|e| match e {
+                CollectError::ReadOutput(source) => PipelineStageErrorKind::ReadOutput { source },
+                CollectError::Wait(e) => e.into(),
+            });
+        let mut results = upstream
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            .into_iter()
+            .map(|result| result.map_err(PipelineStageErrorKind::from))
+            .collect::<Vec<_>>();
+        results.push(last_result);
+
+        match self.check(results) {
+            Ok(status) => {
+                log::debug!("pipeline {self} succeeded");
+                Ok(Output {
+                    status,
+                    stdout,
+                    stderr,
+                })
+            }
+            Err(e) => Err((e, stderr)),
+        }
+    }
+
+    /// Execute this pipeline, capturing the output of its last command, and returning an error if
+    /// it did not succeed.
+    ///
+    /// Like [`EasyCommand::output`], the first command's `stdin` is null, and the last command's
+    /// `stdout` and `stderr` are captured. The `stderr` of other commands is inherited from the
+    /// parent. If the last command fails, the returned error's [`Display`] implementation includes
+    /// the last few lines of its `stderr`.
+    pub fn output(&mut self) -> Result<Output, ExecuteError<PipelineErrorKind>> {
+        self.output_impl().map_err(|(source, stderr)| {
+            let stderr_tail = if source.stage == self.stages.len() - 1 {
+                OutputTail::stderr(&stderr)
+            } else {
+                None
+            };
+            ExecuteError::for_invocation(self.invocation(), source).with_output_tail(stderr_tail)
+        })
+    }
+
+    fn invocation(&self) -> EasyCommandInvocation {
+        EasyCommandInvocation::pipeline(&self.stages)
+    }
+}
+
+impl Display for Pipeline {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}`", self.invocation())
+    }
+}
+
+/// The specific error case encountered with [`Pipeline::run`] and [`Pipeline::output`], naming
+/// the command in the pipeline that failed.
+#[derive(Debug, thiserror::Error)]
+#[error("stage {} of the pipeline, {cmd}, failed", stage + 1)]
+pub struct PipelineErrorKind {
+    /// The zero-based index of the command that failed.
+    pub stage: usize,
+    cmd: EasyCommandInvocation,
+    pub source: PipelineStageErrorKind,
+}
+
+impl PipelineErrorKind {
+    fn new(stage: usize, cmd: &EasyCommand, source: PipelineStageErrorKind) -> Self {
+        Self {
+            stage,
+            cmd: EasyCommandInvocation::new(cmd),
+            source,
+        }
+    }
+}
+
+/// The specific error case encountered with a single command in a [`Pipeline`].
+#[derive(Debug, thiserror::Error)]
+pub enum PipelineStageErrorKind {
+    #[error(transparent)]
+    Run(#[from] RunErrorKind),
+    #[error("failed to read output")]
+    ReadOutput { source: io::Error },
+}
+
+impl From<WaitError> for PipelineStageErrorKind {
+    fn from(e: WaitError) -> Self {
+        Self::Run(SpawnAndWaitErrorKind::from(e).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        executor::{self, FakeExecutor, FakeResponse},
+        SpawnError,
+    };
+
+    /// Runs a pipeline of `codes.len()` stages, each exiting with the corresponding code.
+    fn run_fake(codes: &[i32], pipefail: bool) -> Result<(), ExecuteError<PipelineErrorKind>> {
+        let fake = Arc::new(FakeExecutor::new());
+        let mut stages = codes.iter().enumerate().map(|(idx, &code)| {
+            let program = format!("stage{idx}");
+            fake.on_program(program.as_str(), FakeResponse::exit_code(code));
+            EasyCommand::new(program)
+        });
+        let mut pipeline = Pipeline::new(stages.next().unwrap());
+        for stage in stages {
+            pipeline.pipe(stage);
+        }
+        pipeline.pipefail(pipefail);
+        executor::with_default(fake, || pipeline.run())
+    }
+
+    fn failed_stage(result: Result<(), ExecuteError<PipelineErrorKind>>) -> (usize, Option<i32>) {
+        let PipelineErrorKind { stage, source, .. } = result.unwrap_err().source;
+        match source {
+            PipelineStageErrorKind::Run(RunErrorKind::UnsuccessfulExitCode { code, .. }) => {
+                (stage, code)
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[test]
+    fn only_last_stage_counts_without_pipefail() {
+        assert!(run_fake(&[1, 2, 0], false).is_ok());
+        assert_eq!(failed_stage(run_fake(&[1, 0, 3], false)), (2, Some(3)));
+    }
+
+    #[test]
+    fn pipefail_reports_last_failing_stage() {
+        assert!(run_fake(&[0, 0, 0], true).is_ok());
+        assert_eq!(failed_stage(run_fake(&[1, 2, 0], true)), (1, Some(2)));
+        assert_eq!(failed_stage(run_fake(&[1, 0, 0], true)), (0, Some(1)));
+        assert_eq!(failed_stage(run_fake(&[1, 0, 3], true)), (2, Some(3)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stages_are_connected() {
+        let output = EasyCommand::simple("echo", ["hello"])
+            .pipe(EasyCommand::simple("tr", ["a-z", "A-Z"]))
+            .output()
+            .unwrap();
+        assert_eq!(output.stdout, b"HELLO\n");
+
+        let output = EasyCommand::simple("printf", ["b\\na\\nc\\n"])
+            .pipe(EasyCommand::new("sort"))
+            .pipe(EasyCommand::simple("head", ["-n1"]))
+            .output()
+            .unwrap();
+        assert_eq!(output.stdout, b"a\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn false_into_cat_fails_only_with_pipefail() {
+        let mut pipeline = EasyCommand::new("false").pipe(EasyCommand::new("cat"));
+        assert!(pipeline.output().is_ok());
+        let e = pipeline.pipefail(true).output().unwrap_err();
+        assert_eq!(e.source.stage, 0);
+        assert!(e.to_string().contains("false | cat"), "{e}");
+        assert_eq!(
+            e.source.to_string(),
+            "stage 1 of the pipeline, false, failed"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stderr_tail_is_shown_only_for_last_stage() {
+        let stage = |script: &str| {
+            let mut cmd = EasyCommand::simple("sh", ["-c", script]);
+            cmd.stderr(Stdio::null());
+            cmd
+        };
+        let mut pipeline = stage("exit 1").pipe(EasyCommand::simple(
+            "sh",
+            ["-c", "echo $((6 * 7)) >&2; exit 2"],
+        ));
+        let e = pipeline.output().unwrap_err();
+        assert_eq!(e.source.stage, 1);
+        assert!(e.to_string().contains("42"), "{e}");
+
+        let mut pipeline =
+            stage("exit 1").pipe(EasyCommand::simple("sh", ["-c", "echo $((6 * 7)) >&2"]));
+        let e = pipeline.pipefail(true).output().unwrap_err();
+        assert_eq!(e.source.stage, 0);
+        assert!(!e.to_string().contains("42"), "{e}");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn spawn_failure_blames_stage_and_terminates_earlier_stages() {
+        let marker = std::env::temp_dir().join(format!("ezcmd-test-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let first = EasyCommand::new_with("sh", |cmd| {
+            cmd.args(["-c", "sleep 0.5; touch \"$0\""]).arg(&marker)
+        });
+        let e = first
+            .pipe(EasyCommand::new("ezcmd-test-no-such-program"))
+            .pipe(EasyCommand::new("cat"))
+            .run()
+            .unwrap_err();
+        assert_eq!(e.source.stage, 1);
+        assert!(matches!(
+            e.source.source,
+            PipelineStageErrorKind::Run(RunErrorKind::SpawnAndWait(SpawnAndWaitErrorKind::Spawn {
+                source: SpawnError::ProgramNotFound(_)
+            }))
+        ));
+        thread::sleep(std::time::Duration::from_secs(1));
+        assert!(!marker.exists(), "the first stage was not terminated");
+    }
+}
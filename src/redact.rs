@@ -0,0 +1,200 @@
+use std::{
+    collections::BTreeSet,
+    ffi::{OsStr, OsString},
+    ops::Range,
+    sync::{PoisonError, RwLock},
+};
+
+use regex_lite::Regex;
+
+/// What secrets are displayed as in command lines.
+const REDACTED: &str = "***";
+
+/// A rule for hiding secrets in command lines, as displayed in errors and logs, by a regular
+/// expression that they match.
+///
+/// A redaction can apply to a single command with
+/// [`EasyCommand::redact`](crate::EasyCommand::redact), or to all commands in this process with
+/// [`Redaction::add_global`].
+#[derive(Clone, Debug)]
+pub struct Redaction {
+    pattern: Regex,
+}
+
+static GLOBAL: RwLock<Vec<Redaction>> = RwLock::new(Vec::new());
+
+impl Redaction {
+    /// Hide whatever matches `pattern` in each argument, and in each environment variable
+    /// assignment, like `TOKEN=...`, of a command line. If `pattern` has a capture group named
+    /// `secret`, only that is hidden, like with `--password=(?<secret>.*)`.
+    ///
+    /// Only the values of environment variables are hidden, like `TOKEN=***`.
+    pub fn new(pattern: &str) -> Result<Self, regex_lite::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+        })
+    }
+
+    /// Apply this redaction to all commands in this process, in addition to those already added.
+    pub fn add_global(self) {
+        GLOBAL
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(self);
+    }
+
+    /// The ranges of `text` that this redaction hides.
+    fn secret_ranges<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.pattern.captures_iter(text).filter_map(|captures| {
+            captures
+                .name("secret")
+                .or_else(|| captures.get(0))
+                .map(|secret| secret.range())
+                .filter(|range| !range.is_empty())
+        })
+    }
+}
+
+/// The parts of an [`EasyCommand`](crate::EasyCommand) to hide when displaying it.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Secrets {
+    /// The indices of arguments that are secret.
+    pub args: BTreeSet<usize>,
+    /// The names of environment variables whose values are secret.
+    pub envs: BTreeSet<OsString>,
+    pub redactions: Vec<Redaction>,
+}
+
+impl Secrets {
+    /// Quotes the argument at `idx` for a shell, with any secrets hidden.
+    pub fn quote_arg(&self, idx: usize, arg: &str) -> String {
+        if self.args.contains(&idx) {
+            REDACTED.to_owned()
+        } else {
+            self.quote("", arg)
+        }
+    }
+
+    /// Quotes the value of the environment variable `key` for a shell, with any secrets hidden.
+    pub fn quote_env_value(&self, key: &OsStr, value: &str) -> String {
+        if self.envs.contains(key) {
+            REDACTED.to_owned()
+        } else {
+            self.quote(&format!("{}=", key.to_string_lossy()), value)
+        }
+    }
+
+    /// Quotes `word` for a shell, with `***` in place of the parts of it that any redaction
+    /// matches, when matched against it following `prefix`.
+    pub fn quote(&self, prefix: &str, word: &str) -> String {
+        let text = format!("{prefix}{word}");
+        let global = GLOBAL.read().unwrap_or_else(PoisonError::into_inner);
+        let mut secret_ranges = self
+            .redactions
+            .iter()
+            .chain(global.iter())
+            .flat_map(|redaction| redaction.secret_ranges(&text).collect::<Vec<_>>())
+            .map(|range| {
+                range.start.saturating_sub(prefix.len())..range.end.saturating_sub(prefix.len())
+            })
+            .filter(|range| !range.is_empty())
+            .collect::<Vec<_>>();
+        if secret_ranges.is_empty() {
+            return ::shell_words::quote(word).into_owned();
+        }
+
+        secret_ranges.sort_by_key(|range| range.start);
+        let mut quoted = String::new();
+        let mut plain_start = 0;
+        for range in secret_ranges {
+            if range.start > plain_start {
+                quoted += &::shell_words::quote(&word[plain_start..range.start]);
+                quoted += REDACTED;
+            } else if plain_start == 0 {
+                quoted += REDACTED;
+            }
+            plain_start = plain_start.max(range.end);
+        }
+        if plain_start < word.len() {
+            quoted += &::shell_words::quote(&word[plain_start..]);
+        }
+        quoted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secrets(patterns: &[&str]) -> Secrets {
+        Secrets {
+            redactions: patterns
+                .iter()
+                .map(|pattern| Redaction::new(pattern).unwrap())
+                .collect(),
+            ..Secrets::default()
+        }
+    }
+
+    #[test]
+    fn quote_without_secrets_quotes_for_shell() {
+        assert_eq!(secrets(&[]).quote("", "a b"), "'a b'");
+    }
+
+    #[test]
+    fn quote_hides_whole_match() {
+        let secrets = secrets(&["hunter2"]);
+        assert_eq!(secrets.quote("", "hunter2"), "***");
+        assert_eq!(secrets.quote("", "--pw=hunter2!"), "'--pw='***!");
+    }
+
+    #[test]
+    fn quote_hides_only_secret_group() {
+        let secrets = secrets(&["--password=(?<secret>.*)"]);
+        assert_eq!(secrets.quote("", "--password=a b"), "'--password='***");
+    }
+
+    #[test]
+    fn quote_merges_overlapping_and_adjacent_matches() {
+        let secrets = secrets(&["abc", "bcd", "ef"]);
+        assert_eq!(secrets.quote("", "xabcdefy"), "x***y");
+        assert_eq!(secrets.quote("", "abcd ef"), "***' '***");
+    }
+
+    #[test]
+    fn quote_merges_contained_matches() {
+        let secrets = secrets(&["abcdef", "cd", "b"]);
+        assert_eq!(secrets.quote("", "xabcdefy"), "x***y");
+        assert_eq!(secrets.quote("", "b b"), "***' '***");
+    }
+
+    #[test]
+    fn quote_hides_only_part_of_match_after_prefix() {
+        let secrets = secrets(&["N=ab"]);
+        assert_eq!(secrets.quote("N=", "abc"), "***c");
+        assert_eq!(secrets.quote("N=", "xab"), "xab");
+    }
+
+    #[test]
+    fn quote_matches_after_prefix_without_hiding_it() {
+        let secrets = secrets(&["TOKEN=(?<secret>.*)"]);
+        assert_eq!(secrets.quote("TOKEN=", "abc"), "***");
+        assert_eq!(secrets.quote("OTHER=", "abc"), "abc");
+    }
+
+    #[test]
+    fn secret_args_and_envs_are_hidden_entirely() {
+        let secrets = Secrets {
+            args: BTreeSet::from([1]),
+            envs: BTreeSet::from([OsString::from("TOKEN")]),
+            ..Secrets::default()
+        };
+        assert_eq!(secrets.quote_arg(0, "--token"), "--token");
+        assert_eq!(secrets.quote_arg(1, "a b"), "***");
+        assert_eq!(secrets.quote_env_value(OsStr::new("TOKEN"), "abc"), "***");
+        assert_eq!(
+            secrets.quote_env_value(OsStr::new("HOME"), "/a b"),
+            "'/a b'"
+        );
+    }
+}
@@ -0,0 +1,653 @@
+//! Locating programs the way that child processes are spawned, both to check for them up front and
+//! to diagnose spawn failures.
+
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::EasyCommand;
+
+/// A failure to spawn a child process, classified by its most likely cause.
+#[derive(Debug, thiserror::Error)]
+pub enum SpawnError {
+    #[error(transparent)]
+    ProgramNotFound(ProgramNotFound),
+    /// The working directory of the child process does not exist.
+    #[error("working directory {} does not exist", dir.display())]
+    CurrentDirNotFound { dir: PathBuf, source: io::Error },
+    /// The program exists, but cannot be executed, i.e., because it lacks execute permission or is a
+    /// directory.
+    #[error("{} is not executable", path.display())]
+    NotExecutable { path: PathBuf, source: io::Error },
+    /// The program is a script whose interpreter, named in its `#!` line, does not exist.
+    #[error(
+        "interpreter {} of script {} does not exist",
+        interpreter.display(),
+        script.display()
+    )]
+    InterpreterNotFound {
+        script: PathBuf,
+        interpreter: PathBuf,
+        source: io::Error,
+    },
+    #[error(transparent)]
+    Other(io::Error),
+}
+
+/// A program that does not exist.
+#[derive(Debug, thiserror::Error)]
+#[error("{}", DisplayProgramNotFound(self))]
+pub struct ProgramNotFound {
+    /// The program, as given to [`EasyCommand::new`](crate::EasyCommand::new).
+    pub program: PathBuf,
+    /// The directories of `PATH` that were searched, if the program was a bare name. If `PATH`
+    /// is unset, these are the default directories searched instead, as by `execvp`.
+    pub searched: Vec<PathBuf>,
+    /// The names of the executables in `PATH` most similar to that of the program, if any.
+    pub suggestions: Vec<String>,
+}
+
+struct DisplayProgramNotFound<'a>(&'a ProgramNotFound);
+
+impl Display for DisplayProgramNotFound<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ProgramNotFound {
+            program,
+            searched,
+            suggestions,
+        } = self.0;
+        if !is_bare_name(program) {
+            return write!(f, "{} does not exist", program.display());
+        }
+        write!(f, "`{}` was not found in `PATH`", program.display())?;
+        if searched.is_empty() {
+            write!(f, ", which is empty")?;
+        } else {
+            write!(f, " (searched ")?;
+            for (idx, dir) in searched.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", dir.display())?;
+            }
+            write!(f, ")")?;
+        }
+        for (idx, suggestion) in suggestions.iter().enumerate() {
+            match idx {
+                0 => write!(f, "; did you mean `{suggestion}`")?,
+                _ if idx == suggestions.len() - 1 => write!(f, " or `{suggestion}`")?,
+                _ => write!(f, ", `{suggestion}`")?,
+            }
+        }
+        if !suggestions.is_empty() {
+            write!(f, "?")?;
+        }
+        Ok(())
+    }
+}
+
+impl EasyCommand {
+    /// Find the executable that this command would run.
+    ///
+    /// If the program is a bare name, like `git`, it is searched for in `PATH`, as overridden in
+    /// this command's environment, if it is. Otherwise, it is taken to be a path, relative to this
+    /// command's working directory, if it has one.
+    ///
+    /// If `PATH` is unset for this command, i.e., with [`Self::env_remove`] or
+    /// [`Self::env_clear`], then on Unix, the default directories that `execvp` searches instead,
+    /// as given by `confstr(_CS_PATH)`, are searched, like `/bin` and `/usr/bin`. Elsewhere,
+    /// nothing is searched, though Windows itself searches its system directories in that case.
+    pub fn resolve_program(&self) -> Result<PathBuf, ProgramNotFound> {
+        find_program(&self.inner, self.env_cleared).map_err(|SearchFailure { searched, .. }| {
+            ProgramNotFound::new(self.inner.get_program(), searched)
+        })
+    }
+}
+
+/// Check that the programs of all `cmds` exist, with [`EasyCommand::resolve_program`], before
+/// executing any of them.
+///
+/// This is useful for failing early, rather than midway through a long job.
+pub fn preflight<'a, I>(cmds: I) -> Result<(), MissingProgramsError>
+where
+    I: IntoIterator<Item = &'a EasyCommand>,
+{
+    let mut missing = Vec::<ProgramNotFound>::new();
+    for cmd in cmds {
+        if let Err(e) = cmd.resolve_program() {
+            if !missing.iter().any(|m| m.program == e.program) {
+                missing.push(e);
+            }
+        }
+    }
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(MissingProgramsError { missing })
+    }
+}
+
+/// The error returned by [`preflight`], listing every program that was not found.
+#[derive(Debug, thiserror::Error)]
+#[error("{}", DisplayMissingPrograms(missing))]
+pub struct MissingProgramsError {
+    pub missing: Vec<ProgramNotFound>,
+}
+
+struct DisplayMissingPrograms<'a>(&'a [ProgramNotFound]);
+
+impl Display for DisplayMissingPrograms<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Self(missing) = self;
+        match missing.len() {
+            1 => write!(f, "1 required program is missing:")?,
+            len => write!(f, "{len} required programs are missing:")?,
+        }
+        for program in missing.iter() {
+            write!(f, "\n    {program}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Classifies a failure to spawn `cmd` by inspecting the file system. `env_cleared` is whether
+/// the environment of `cmd` was cleared.
+pub(crate) fn classify_spawn_error(
+    cmd: &Command,
+    env_cleared: bool,
+    source: io::Error,
+) -> SpawnError {
+    match source.kind() {
+        io::ErrorKind::NotFound => {
+            if let Some(dir) = cmd.get_current_dir() {
+                if !dir.is_dir() {
+                    return SpawnError::CurrentDirNotFound {
+                        dir: dir.to_owned(),
+                        source,
+                    };
+                }
+            }
+            match find_program(cmd, env_cleared) {
+                Err(SearchFailure { searched, .. }) => {
+                    SpawnError::ProgramNotFound(ProgramNotFound::new(cmd.get_program(), searched))
+                }
+                Ok(script) => match shebang_interpreter(&script) {
+                    Some(interpreter) if !interpreter.exists() => SpawnError::InterpreterNotFound {
+                        script,
+                        interpreter,
+                        source,
+                    },
+                    _ => SpawnError::Other(source),
+                },
+            }
+        }
+        io::ErrorKind::PermissionDenied => match find_program(cmd, env_cleared) {
+            Err(SearchFailure {
+                non_executable: Some(path),
+                ..
+            }) => SpawnError::NotExecutable { path, source },
+            _ => SpawnError::Other(source),
+        },
+        _ => SpawnError::Other(source),
+    }
+}
+
+impl ProgramNotFound {
+    fn new(program: &OsStr, searched: Vec<PathBuf>) -> Self {
+        let program = PathBuf::from(program);
+        let suggestions = if is_bare_name(&program) {
+            suggest(&program.to_string_lossy(), &searched)
+        } else {
+            Vec::new()
+        };
+        Self {
+            program,
+            searched,
+            suggestions,
+        }
+    }
+}
+
+/// The result of failing to find a program.
+pub(crate) struct SearchFailure {
+    /// The directories of `PATH` that were searched, if the program was a bare name.
+    pub searched: Vec<PathBuf>,
+    /// The first file found with the program's name that could not be executed, like a file that
+    /// lacks execute permission, or a directory.
+    pub non_executable: Option<PathBuf>,
+}
+
+/// Finds the executable that would be spawned for `cmd`, searching the `PATH` of its environment
+/// if its program is a bare name. `env_cleared` is whether that environment was cleared.
+pub(crate) fn find_program(cmd: &Command, env_cleared: bool) -> Result<PathBuf, SearchFailure> {
+    let program = Path::new(cmd.get_program());
+    let mut non_executable = None;
+    let mut check = |path: PathBuf| {
+        if is_executable(&path) {
+            Some(path)
+        } else {
+            if non_executable.is_none() && path.exists() {
+                non_executable = Some(path);
+            }
+            None
+        }
+    };
+
+    if !is_bare_name(program) {
+        let path = match cmd.get_current_dir() {
+            Some(dir) if program.is_relative() => dir.join(program),
+            _ => program.to_owned(),
+        };
+        return match check(path) {
+            Some(path) => Ok(path),
+            None => Err(SearchFailure {
+                searched: Vec::new(),
+                non_executable,
+            }),
+        };
+    }
+
+    let searched = search_path(cmd, env_cleared);
+    for dir in &searched {
+        for candidate in candidates(&dir.join(program)) {
+            if let Some(path) = check(candidate) {
+                return Ok(path);
+            }
+        }
+    }
+    Err(SearchFailure {
+        searched,
+        non_executable,
+    })
+}
+
+/// The directories of `PATH` in `cmd`'s environment, which may be overridden from that of the
+/// current process, or cleared, if `env_cleared`. If `PATH` is unset, these are the directories
+/// searched by default instead.
+pub(crate) fn search_path(cmd: &Command, env_cleared: bool) -> Vec<PathBuf> {
+    let overridden = cmd
+        .get_envs()
+        .filter(|(key, _value)| is_path_var(key))
+        .last()
+        .map(|(_key, value)| value.map(ToOwned::to_owned));
+    let path = match overridden {
+        Some(path) => path,
+        None if env_cleared => None,
+        None => env::var_os("PATH"),
+    };
+    path.or_else(default_search_path)
+        .map(|path| env::split_paths(&path).collect())
+        .unwrap_or_default()
+}
+
+/// The directories that `execvp`, and so [`Command::spawn`], searches when `PATH` is unset.
+#[cfg(unix)]
+fn default_search_path() -> Option<OsString> {
+    use std::os::unix::ffi::OsStringExt;
+
+    // SAFETY: With a null buffer of length 0, `confstr` only returns the length it needs.
+    let len = unsafe { libc::confstr(libc::_CS_PATH, std::ptr::null_mut(), 0) };
+    if len == 0 {
+        return None;
+    }
+    let mut buf = vec![0_u8; len];
+    // SAFETY: `buf` is valid for writes of `len` bytes.
+    let written = unsafe { libc::confstr(libc::_CS_PATH, buf.as_mut_ptr().cast(), len) };
+    if written == 0 || written > len {
+        return None;
+    }
+    buf.truncate(written - 1);
+    Some(OsString::from_vec(buf))
+}
+
+#[cfg(not(unix))]
+fn default_search_path() -> Option<OsString> {
+    None
+}
+
+fn is_path_var(key: &OsStr) -> bool {
+    if cfg!(windows) {
+        key.eq_ignore_ascii_case("PATH")
+    } else {
+        key == "PATH"
+    }
+}
+
+/// Whether `program` would be searched for in `PATH`, rather than being treated as a path.
+fn is_bare_name(program: &Path) -> bool {
+    program.components().count() == 1 && !program.has_root()
+}
+
+/// The file names that `path` may be executed as. On Windows, this includes those with the
+/// extensions listed in `PATHEXT`.
+fn candidates(path: &Path) -> Vec<PathBuf> {
+    let mut candidates = vec![path.to_owned()];
+    if cfg!(windows) && path.extension().is_none() {
+        let extensions = env::var_os("PATHEXT").unwrap_or_else(|| ".COM;.EXE;.BAT;.CMD".into());
+        for extension in extensions.to_string_lossy().split(';') {
+            let mut candidate = OsString::from(path);
+            candidate.push(extension);
+            candidates.push(candidate.into());
+        }
+    }
+    candidates
+}
+
+/// The names of the executables in `dirs` that are most similar to `program`, if any are within a
+/// small edit distance of it.
+fn suggest(program: &str, dirs: &[PathBuf]) -> Vec<String> {
+    const MAX_SUGGESTIONS: usize = 3;
+
+    let max_distance = match program.chars().count() {
+        0..=2 => 1,
+        3..=5 => 2,
+        _ => 3,
+    };
+    let mut suggestions = dirs
+        .iter()
+        .filter_map(|dir| dir.read_dir().ok())
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = if cfg!(windows) {
+                path.file_stem()
+            } else {
+                path.file_name()
+            }?
+            .to_str()?
+            .to_owned();
+            let distance = edit_distance(program, &name);
+            (distance <= max_distance && is_executable(&path)).then_some((distance, name))
+        })
+        .collect::<Vec<_>>();
+    suggestions.sort();
+    suggestions.dedup();
+    let closest = suggestions.first().map(|(distance, _name)| *distance);
+    suggestions
+        .into_iter()
+        .take_while(|(distance, _name)| Some(*distance) == closest)
+        .take(MAX_SUGGESTIONS)
+        .map(|(_distance, name)| name)
+        .collect()
+}
+
+/// The number of single-`char` insertions, deletions, substitutions, and transpositions of
+/// adjacent `char`s needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = substitution
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// The interpreter named in the `#!` line of the script at `path`, if any.
+fn shebang_interpreter(path: &Path) -> Option<PathBuf> {
+    let mut head = [0; 256];
+    let mut file = File::open(path).ok()?;
+    let len = file.read(&mut head).ok()?;
+    let line = head[..len]
+        .strip_prefix(b"#!")?
+        .split(|&b| b == b'\n')
+        .next()?;
+    let line = String::from_utf8_lossy(line);
+    line.split_whitespace().next().map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use crate::SpawnAndWaitErrorKind;
+
+    #[cfg(unix)]
+    /// A directory that is removed when dropped.
+    struct TempDir(PathBuf);
+
+    #[cfg(unix)]
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("ezcmd-test-{}-{name}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        /// Creates a file in this directory with `contents` and permissions `mode`.
+        fn file(&self, name: &str, contents: &str, mode: u32) -> PathBuf {
+            use std::os::unix::fs::PermissionsExt;
+
+            let path = self.0.join(name);
+            std::fs::write(&path, contents).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            path
+        }
+    }
+
+    #[cfg(unix)]
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[cfg(unix)]
+    fn spawn_error(mut cmd: EasyCommand) -> SpawnError {
+        match cmd.spawn_and_wait().unwrap_err().source {
+            SpawnAndWaitErrorKind::Spawn { source } => source,
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn classifies_non_executable_program() {
+        let dir = TempDir::new("not-executable");
+        let script = dir.file("script", "#!/bin/sh\n", 0o644);
+        match spawn_error(EasyCommand::new(&script)) {
+            SpawnError::NotExecutable { path, .. } => assert_eq!(path, script),
+            e => panic!("unexpected error: {e:?}"),
+        }
+        match spawn_error(EasyCommand::new(&dir.0)) {
+            SpawnError::NotExecutable { path, .. } => assert_eq!(path, dir.0),
+            e => panic!("unexpected error: {e:?}"),
+        }
+
+        let mut cmd = EasyCommand::new("script");
+        cmd.env("PATH", &dir.0);
+        match spawn_error(cmd) {
+            SpawnError::NotExecutable { path, .. } => assert_eq!(path, script),
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn classifies_missing_interpreter() {
+        let dir = TempDir::new("interpreter");
+        let script = dir.file("script", "#!/ezcmd-test/no-such-sh -e\ntrue\n", 0o755);
+        match spawn_error(EasyCommand::new(&script)) {
+            SpawnError::InterpreterNotFound {
+                script: found,
+                interpreter,
+                ..
+            } => {
+                assert_eq!(found, script);
+                assert_eq!(interpreter, Path::new("/ezcmd-test/no-such-sh"));
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn classifies_missing_current_dir() {
+        let dir = TempDir::new("current-dir");
+        let missing = dir.0.join("missing");
+        let mut cmd = EasyCommand::new("sh");
+        cmd.current_dir(&missing);
+        match spawn_error(cmd) {
+            SpawnError::CurrentDirNotFound { dir, .. } => assert_eq!(dir, missing),
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn classifies_missing_program_with_searched_dirs() {
+        let (a, b) = (TempDir::new("path-a"), TempDir::new("path-b"));
+        let mut cmd = EasyCommand::new("ezcmd-test-prog");
+        cmd.env("PATH", env::join_paths([&a.0, &b.0]).unwrap());
+        match spawn_error(cmd) {
+            SpawnError::ProgramNotFound(e) => {
+                assert_eq!(e.searched, [a.0.clone(), b.0.clone()]);
+                assert_eq!(
+                    e.to_string(),
+                    format!(
+                        "`ezcmd-test-prog` was not found in `PATH` (searched {}, {})",
+                        a.0.display(),
+                        b.0.display()
+                    )
+                );
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+
+        match spawn_error(EasyCommand::new(a.0.join("missing"))) {
+            SpawnError::ProgramNotFound(e) => {
+                assert!(e.searched.is_empty());
+                assert!(e.to_string().ends_with("missing does not exist"), "{e}");
+            }
+            e => panic!("unexpected error: {e:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    fn default_dirs() -> Vec<PathBuf> {
+        env::split_paths(&default_search_path().unwrap()).collect()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unset_path_falls_back_to_default_search_path() {
+        let mut cmd = EasyCommand::simple("sh", ["-c", "true"]);
+        cmd.env_remove("PATH");
+        assert!(cmd.resolve_program().is_ok());
+        assert!(preflight([&cmd]).is_ok());
+        cmd.run().unwrap();
+
+        let mut cmd = EasyCommand::new("ezcmd-test-no-such-program");
+        cmd.env_remove("PATH");
+        assert_eq!(cmd.resolve_program().unwrap_err().searched, default_dirs());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cleared_env_does_not_search_parent_path() {
+        let mut cmd = EasyCommand::new("ezcmd-test-no-such-program");
+        cmd.env_clear();
+        assert_eq!(cmd.resolve_program().unwrap_err().searched, default_dirs());
+
+        let mut cmd = EasyCommand::simple("sh", ["-c", "true"]);
+        cmd.env_clear();
+        assert!(cmd.resolve_program().is_ok());
+        cmd.run().unwrap();
+
+        let mut cmd = EasyCommand::new("ezcmd-test-no-such-program");
+        cmd.env_clear().env("PATH", "/ezcmd-test-a:/ezcmd-test-b");
+        assert_eq!(
+            cmd.resolve_program().unwrap_err().searched,
+            [Path::new("/ezcmd-test-a"), Path::new("/ezcmd-test-b")]
+        );
+    }
+
+    #[test]
+    fn edit_distance_counts_single_char_edits() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("git", "git"), 0);
+        assert_eq!(edit_distance("", "git"), 3);
+        // Insertion, deletion, and substitution.
+        assert_eq!(edit_distance("gt", "git"), 1);
+        assert_eq!(edit_distance("gitt", "git"), 1);
+        assert_eq!(edit_distance("gut", "git"), 1);
+        // Transposition of adjacent chars.
+        assert_eq!(edit_distance("gti", "git"), 1);
+        assert_eq!(edit_distance("crago", "cargo"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("é", "e"), 1);
+    }
+
+    #[cfg(unix)]
+    fn suggestions(program: &str, executables: &[&str]) -> Vec<String> {
+        let dir = TempDir::new(&format!("suggest-{program}"));
+        for name in executables {
+            dir.file(name, "", 0o755);
+        }
+        suggest(program, std::slice::from_ref(&dir.0))
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn suggest_only_executables_with_closest_distance() {
+        assert_eq!(suggestions("mkae", &["make", "mace", "cmake"]), ["make"]);
+
+        let dir = TempDir::new("suggest-non-executable");
+        dir.file("make", "", 0o644);
+        assert!(suggest("mkae", std::slice::from_ref(&dir.0)).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn suggest_at_most_three_in_order() {
+        assert_eq!(
+            suggestions("cargo", &["crago", "cargp", "cargoo", "carg", "carrot"]),
+            ["carg", "cargoo", "cargp"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn suggest_allows_more_distance_for_longer_names() {
+        assert!(suggestions("ab", &["xy"]).is_empty());
+        assert_eq!(suggestions("ab", &["abc"]), ["abc"]);
+        assert_eq!(suggestions("abcd", &["axyd"]), ["axyd"]);
+        assert!(suggestions("abcde", &["axyze"]).is_empty());
+        assert_eq!(suggestions("abcdef", &["abcxyz"]), ["abcxyz"]);
+        assert!(suggestions("abcdef", &["awxyzf"]).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    process::ExitStatus,
+};
+
+/// A Unix signal, as sent to or received by a child process.
+///
+/// Constants are provided for signals whose numbers are the same on all Unix platforms. Other
+/// signals can be made with [`Signal::from_number`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Signal(i32);
+
+impl Signal {
+    pub const HUP: Self = Self(1);
+    pub const INT: Self = Self(2);
+    pub const QUIT: Self = Self(3);
+    pub const ABRT: Self = Self(6);
+    pub const KILL: Self = Self(9);
+    pub const SEGV: Self = Self(11);
+    pub const PIPE: Self = Self(13);
+    pub const TERM: Self = Self(15);
+
+    /// A signal with the given platform-specific number.
+    pub const fn from_number(number: i32) -> Self {
+        Self(number)
+    }
+
+    /// This signal's platform-specific number.
+    pub const fn number(self) -> i32 {
+        self.0
+    }
+
+    /// This signal's conventional name on the current platform, i.e., `SIGKILL`.
+    pub fn name(self) -> Option<&'static str> {
+        name(self.0)
+    }
+
+    /// The signal that terminated a child process with the given `status`, if any, and whether it
+    /// dumped core.
+    pub(crate) fn of_exit(status: &ExitStatus) -> (Option<Self>, bool) {
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+
+            (status.signal().map(Self), status.core_dumped())
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = status;
+            (None, false)
+        }
+    }
+}
+
+impl Display for Signal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Self(number) = self;
+        write!(f, "signal {number}")?;
+        if let Some(name) = self.name() {
+            write!(f, " ({name})")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn name(number: i32) -> Option<&'static str> {
+    Some(match number {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGTRAP => "SIGTRAP",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGUSR1 => "SIGUSR1",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGUSR2 => "SIGUSR2",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGCHLD => "SIGCHLD",
+        libc::SIGCONT => "SIGCONT",
+        libc::SIGSTOP => "SIGSTOP",
+        libc::SIGTSTP => "SIGTSTP",
+        libc::SIGTTIN => "SIGTTIN",
+        libc::SIGTTOU => "SIGTTOU",
+        libc::SIGURG => "SIGURG",
+        libc::SIGXCPU => "SIGXCPU",
+        libc::SIGXFSZ => "SIGXFSZ",
+        libc::SIGVTALRM => "SIGVTALRM",
+        libc::SIGPROF => "SIGPROF",
+        libc::SIGWINCH => "SIGWINCH",
+        libc::SIGIO => "SIGIO",
+        libc::SIGSYS => "SIGSYS",
+        _ => return None,
+    })
+}
+
+#[cfg(not(unix))]
+fn name(number: i32) -> Option<&'static str> {
+    Some(match Signal(number) {
+        Signal::HUP => "SIGHUP",
+        Signal::INT => "SIGINT",
+        Signal::QUIT => "SIGQUIT",
+        Signal::ABRT => "SIGABRT",
+        Signal::KILL => "SIGKILL",
+        Signal::SEGV => "SIGSEGV",
+        Signal::PIPE => "SIGPIPE",
+        Signal::TERM => "SIGTERM",
+        _ => return None,
+    })
+}
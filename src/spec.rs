@@ -0,0 +1,333 @@
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use crate::EasyCommand;
+
+/// A plain-data description of an [`EasyCommand`], which, unlike one, can be cloned, compared,
+/// and, with the `serde` feature, (de)serialized.
+///
+/// This is useful for keeping a template of a command to execute variations of, or for loading
+/// commands from configuration. Convert it into an [`EasyCommand`] with [`Self::to_command`], or
+/// back with [`EasyCommand::to_spec`].
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct CommandSpec {
+    pub program: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub args: Vec<String>,
+    /// Environment variables to set, or to remove if [`None`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub env: BTreeMap<String, Option<String>>,
+    /// Whether to clear the environment inherited from the parent before applying [`Self::env`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub env_clear: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub current_dir: Option<PathBuf>,
+    /// How to configure `stdin`, or [`None`] to leave it unset, so that methods that connect it,
+    /// like [`EasyCommand::output`], may do so, and it is otherwise inherited.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub stdin: Option<StdioSpec>,
+    /// Like [`Self::stdin`], for `stdout`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub stdout: Option<StdioSpec>,
+    /// Like [`Self::stdin`], for `stderr`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub stderr: Option<StdioSpec>,
+}
+
+impl CommandSpec {
+    /// A specification for running `program` with no arguments or other configuration.
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Create an [`EasyCommand`] as specified.
+    pub fn to_command(&self) -> EasyCommand {
+        let Self {
+            program,
+            args,
+            env,
+            env_clear,
+            current_dir,
+            stdin,
+            stdout,
+            stderr,
+        } = self;
+
+        let mut cmd = EasyCommand::new(program);
+        cmd.args(args);
+        if *env_clear {
+            cmd.env_clear();
+        }
+        for (key, value) in env {
+            match value {
+                Some(value) => cmd.env(key, value),
+                None => cmd.env_remove(key),
+            };
+        }
+        if let Some(dir) = current_dir {
+            cmd.current_dir(dir);
+        }
+        if let Some(spec) = *stdin {
+            cmd.inner.stdin(spec);
+            cmd.stdio.stdin = StdioConfig::Spec(spec);
+        }
+        if let Some(spec) = *stdout {
+            cmd.inner.stdout(spec);
+            cmd.stdio.stdout = StdioConfig::Spec(spec);
+        }
+        if let Some(spec) = *stderr {
+            cmd.inner.stderr(spec);
+            cmd.stdio.stderr = StdioConfig::Spec(spec);
+        }
+        cmd
+    }
+}
+
+impl From<&CommandSpec> for EasyCommand {
+    fn from(spec: &CommandSpec) -> Self {
+        spec.to_command()
+    }
+}
+
+impl From<CommandSpec> for EasyCommand {
+    fn from(spec: CommandSpec) -> Self {
+        spec.to_command()
+    }
+}
+
+/// How a stream of a child process is configured in a [`CommandSpec`], mirroring the constructors
+/// of [`Stdio`].
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum StdioSpec {
+    #[default]
+    Inherit,
+    Null,
+    Piped,
+}
+
+impl From<StdioSpec> for Stdio {
+    fn from(spec: StdioSpec) -> Self {
+        match spec {
+            StdioSpec::Inherit => Stdio::inherit(),
+            StdioSpec::Null => Stdio::null(),
+            StdioSpec::Piped => Stdio::piped(),
+        }
+    }
+}
+
+/// How a stream of an [`EasyCommand`] was configured.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum StdioConfig {
+    /// Not at all, so it is inherited, unless a method that captures or connects it overrides it.
+    #[default]
+    Unset,
+    /// As described by a [`StdioSpec`].
+    Spec(StdioSpec),
+    /// With a [`Stdio`] that cannot be described.
+    Arbitrary,
+}
+
+impl StdioConfig {
+    /// The configuration of a stream configured with `cfg`, which can only be described if it is
+    /// a [`StdioSpec`].
+    pub fn of<T: 'static>(cfg: &T) -> Self {
+        match (cfg as &dyn Any).downcast_ref::<StdioSpec>() {
+            Some(&spec) => Self::Spec(spec),
+            None => Self::Arbitrary,
+        }
+    }
+}
+
+/// The configuration of an [`EasyCommand`]'s streams.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct KnownStdio {
+    pub stdin: StdioConfig,
+    pub stdout: StdioConfig,
+    pub stderr: StdioConfig,
+}
+
+impl KnownStdio {
+    /// The configuration of `cmd`'s streams, as far as can be told.
+    ///
+    /// [`Command`] offers no way to tell which of its streams were configured, except for its
+    /// alternate [`Debug`](std::fmt::Debug) representation, which lists them on the platforms
+    /// that support it. Elsewhere, or should that representation change, all streams are assumed
+    /// to be unset. Since that is not a stable interface, this is only best-effort, as documented
+    /// on [`EasyCommand::new_with`].
+    pub fn of_command(cmd: &Command) -> Self {
+        let debug = format!("{cmd:#?}");
+        // Fields of `Command` itself are indented once, unlike anything nested within them.
+        let config = |stream: &str| {
+            if debug.contains(&format!("\n    {stream}: ")) {
+                StdioConfig::Arbitrary
+            } else {
+                StdioConfig::Unset
+            }
+        };
+        Self {
+            stdin: config("stdin"),
+            stdout: config("stdout"),
+            stderr: config("stderr"),
+        }
+    }
+}
+
+impl EasyCommand {
+    /// Describe this command as a [`CommandSpec`].
+    ///
+    /// This fails if any part of this command is not valid UTF-8, or if any of its streams were
+    /// configured with a [`Stdio`], including in [`Self::new_with`], since which one cannot be
+    /// told. Only streams configured with a [`StdioSpec`], as by a [`CommandSpec`], can be
+    /// described. Note that
+    /// configuration made with [`Self::as_std_mut`] may not be reflected in the result, that the
+    /// contents of any [`Self::input`] are not, and that secrets, like those added with
+    /// [`Self::secret_arg`], are included verbatim.
+    pub fn to_spec(&self) -> Result<CommandSpec, ToSpecError> {
+        let utf8 = |what: &'static str, s: &std::ffi::OsStr| {
+            s.to_str()
+                .map(ToOwned::to_owned)
+                .ok_or_else(|| ToSpecError::NonUtf8 {
+                    what,
+                    lossy: s.to_string_lossy().into_owned(),
+                })
+        };
+        let stdio = |stream: &'static str, config: StdioConfig| match config {
+            StdioConfig::Unset => Ok(None),
+            StdioConfig::Spec(spec) => Ok(Some(spec)),
+            StdioConfig::Arbitrary => Err(ToSpecError::ArbitraryStdio { stream }),
+        };
+
+        let cmd = &self.inner;
+        Ok(CommandSpec {
+            program: utf8("program", cmd.get_program())?,
+            args: cmd
+                .get_args()
+                .map(|arg| utf8("argument", arg))
+                .collect::<Result<_, _>>()?,
+            env: cmd
+                .get_envs()
+                .map(|(key, value)| {
+                    let value = value
+                        .map(|value| utf8("environment variable value", value))
+                        .transpose()?;
+                    Ok((utf8("environment variable name", key)?, value))
+                })
+                .collect::<Result<_, _>>()?,
+            env_clear: self.env_cleared,
+            current_dir: cmd.get_current_dir().map(ToOwned::to_owned),
+            stdin: stdio("stdin", self.stdio.stdin)?,
+            stdout: stdio("stdout", self.stdio.stdout)?,
+            stderr: stdio("stderr", self.stdio.stderr)?,
+        })
+    }
+}
+
+impl TryFrom<&EasyCommand> for CommandSpec {
+    type Error = ToSpecError;
+
+    fn try_from(cmd: &EasyCommand) -> Result<Self, Self::Error> {
+        cmd.to_spec()
+    }
+}
+
+/// An error encountered with [`EasyCommand::to_spec`].
+#[derive(Debug, thiserror::Error)]
+pub enum ToSpecError {
+    #[error("{what} {lossy:?} is not valid UTF-8")]
+    NonUtf8 { what: &'static str, lossy: String },
+    #[error("`{stream}` was configured with a `Stdio` that cannot be described")]
+    ArbitraryStdio { stream: &'static str },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_round_trips() {
+        let spec = CommandSpec {
+            program: "cargo".to_owned(),
+            args: vec!["test".to_owned(), "a b".to_owned()],
+            env: BTreeMap::from([
+                ("RUSTFLAGS".to_owned(), Some("-Dwarnings".to_owned())),
+                ("CARGO_HOME".to_owned(), None),
+            ]),
+            env_clear: false,
+            current_dir: Some(PathBuf::from("/tmp")),
+            stdin: Some(StdioSpec::Null),
+            stdout: None,
+            stderr: Some(StdioSpec::Piped),
+        };
+        assert_eq!(spec.to_command().to_spec().unwrap(), spec);
+    }
+
+    #[test]
+    fn unset_streams_are_described_as_unset() {
+        let spec = EasyCommand::simple("cargo", ["build"]).to_spec().unwrap();
+        assert_eq!(spec.stdin, None);
+        assert_eq!(spec.stdout, None);
+        assert_eq!(spec.stderr, None);
+    }
+
+    #[test]
+    fn streams_configured_with_stdio_cannot_be_described() {
+        let cmd = EasyCommand::new_with("cargo", |cmd| cmd.stdout(Stdio::null()));
+        assert!(matches!(
+            cmd.to_spec(),
+            Err(ToSpecError::ArbitraryStdio { stream: "stdout" })
+        ));
+
+        let mut cmd = EasyCommand::new("cargo");
+        cmd.stderr(Stdio::null());
+        assert!(matches!(
+            cmd.to_spec(),
+            Err(ToSpecError::ArbitraryStdio { stream: "stderr" })
+        ));
+    }
+
+    #[test]
+    fn streams_configured_with_stdio_spec_can_be_described() {
+        let mut cmd = EasyCommand::new("cargo");
+        cmd.stdout(StdioSpec::Null).stderr(StdioSpec::Piped);
+        let spec = cmd.to_spec().unwrap();
+        assert_eq!(spec.stdin, None);
+        assert_eq!(spec.stdout, Some(StdioSpec::Null));
+        assert_eq!(spec.stderr, Some(StdioSpec::Piped));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn spec_deserializes_with_defaults() {
+        let spec: CommandSpec =
+            serde_json::from_str(r#"{"program": "cargo", "stdout": "null"}"#).unwrap();
+        assert_eq!(
+            spec,
+            CommandSpec {
+                stdout: Some(StdioSpec::Null),
+                ..CommandSpec::new("cargo")
+            }
+        );
+    }
+}
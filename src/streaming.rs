@@ -0,0 +1,511 @@
+use std::{
+    io::{self, Write},
+    path::Path,
+    process::{Output, Stdio},
+};
+
+use crate::{
+    check_output, check_status,
+    child::{CollectError, Stream},
+    EasyCommand, ExecuteError, OutputCheckedErrorKind, OutputErrorKind, OutputTail, RunErrorKind,
+    SpawnAndWaitErrorKind,
+};
+
+/// How many bytes of each stream [`EasyCommand::output_tee`] captures.
+const TEE_CAPTURE_LIMIT: usize = 1 << 20;
+
+impl EasyCommand {
+    fn run_streaming_impl(
+        &mut self,
+        on_line: &mut dyn FnMut(Stream, &str),
+        stderr_tail: &mut TailBuffer,
+    ) -> Result<(), RunStreamingErrorKind> {
+        log::debug!("streaming output from {self}…");
+        let mut child = self
+            .spawn_with_stdio(None, Some(Stdio::piped()), Some(Stdio::piped()))
+            .map_err(|source| RunErrorKind::from(SpawnAndWaitErrorKind::Spawn { source }))?;
+
+        log::trace!("waiting for output from `{}`…", child.cmd());
+        let (mut stdout, mut stderr) = (LineBuffer::default(), LineBuffer::default());
+        let status = child.wait_with_output(|stream, data| match stream {
+            Stream::Stdout => stdout.push(data, |line| on_line(stream, line)),
+            Stream::Stderr => {
+                stderr_tail.push(data);
+                stderr.push(data, |line| on_line(stream, line));
+            }
+        });
+        stdout.finish(|line| on_line(Stream::Stdout, line));
+        stderr.finish(|line| on_line(Stream::Stderr, line));
+        let status = status.map_err(|e| match e {
+            CollectError::ReadOutput(source) => RunStreamingErrorKind::ReadOutput { source },
+            CollectError::Wait(e) => RunErrorKind::from(SpawnAndWaitErrorKind::from(e)).into(),
+        })?;
+        log::debug!(
+            "received exit code {:?} from `{}`",
+            status.code(),
+            child.cmd()
+        );
+        Ok(check_status(status)?)
+    }
+
+    /// Execute this command, handing each line of its `stdout` and `stderr` to `on_line` as soon
+    /// as it is read, and returning an error if it did not return a successful exit code.
+    ///
+    /// Lines are passed without their line endings, and with invalid UTF-8 replaced. Both streams
+    /// are read concurrently, so neither can fill up and block the child process, and `on_line` is
+    /// called on the current thread. `stdin` is inherited from the parent.
+    ///
+    /// On failure, the returned error's [`Display`](std::fmt::Display) implementation includes the
+    /// last few lines of `stderr`.
+    pub fn run_streaming(
+        &mut self,
+        mut on_line: impl FnMut(Stream, &str),
+    ) -> Result<(), ExecuteError<RunStreamingErrorKind>> {
+        // Keep more than `OutputTail` shows, so that it can tell whether it was truncated.
+        let mut stderr_tail = TailBuffer::new(2 * OutputTail::MAX_BYTES);
+        self.run_streaming_impl(&mut on_line, &mut stderr_tail)
+            .map_err(|source| {
+                ExecuteError::new(self, source)
+                    .with_output_tail(OutputTail::stderr(stderr_tail.contents()))
+            })
+    }
+
+    /// Like [`Self::run_streaming`], but logging each line of output as configured by `config`,
+    /// rather than handing it to a callback.
+    pub fn run_logged(
+        &mut self,
+        config: &LogOutput,
+    ) -> Result<(), ExecuteError<RunStreamingErrorKind>> {
+        let LogOutput {
+            stdout_level,
+            stderr_level,
+            target,
+            prefix,
+        } = config;
+        let target = target.as_deref().unwrap_or(env!("CARGO_CRATE_NAME"));
+        let prefix = match prefix {
+            Some(prefix) => prefix.clone(),
+            None => {
+                let program = Path::new(self.inner.get_program());
+                let name = program.file_name().unwrap_or(program.as_os_str());
+                format!("[{}]", name.to_string_lossy())
+            }
+        };
+        let separator = if prefix.is_empty() { "" } else { " " };
+        self.run_streaming(|stream, line| {
+            let level = match stream {
+                Stream::Stdout => *stdout_level,
+                Stream::Stderr => *stderr_level,
+            };
+            log::log!(target: target, level, "{prefix}{separator}{line}");
+        })
+    }
+
+    fn output_tee_impl(&mut self) -> Result<Output, OutputCheckedErrorKind> {
+        log::debug!("getting output from {self}, and forwarding it…");
+        let child = self
+            .spawn_with_stdio(None, Some(Stdio::piped()), Some(Stdio::piped()))
+            .map_err(|source| OutputErrorKind::Spawn { source })?;
+        let output = child.output_with(TEE_CAPTURE_LIMIT, forward)?;
+        check_output(output)
+    }
+
+    /// Like [`Self::output_checked`], but also forwarding `stdout` and `stderr` to those of the
+    /// parent as they are read, like `tee`, so that output is both shown live and captured.
+    ///
+    /// Only the last 1 MiB of each stream is captured. `stdin` is inherited from the parent.
+    pub fn output_tee(&mut self) -> Result<Output, ExecuteError<OutputCheckedErrorKind>> {
+        self.output_tee_impl().map_err(|source| {
+            let stderr_tail = source.stderr_tail();
+            ExecuteError::new(self, source).with_output_tail(stderr_tail)
+        })
+    }
+}
+
+/// How [`EasyCommand::run_logged`] turns each line of output into a [`log`] record.
+#[derive(Clone, Debug)]
+pub struct LogOutput {
+    stdout_level: log::Level,
+    stderr_level: log::Level,
+    target: Option<String>,
+    prefix: Option<String>,
+}
+
+impl LogOutput {
+    /// Log `stdout` at the `info` level and `stderr` at the `warn` level, with this crate's
+    /// target, prefixed with the file name of the program, like `[rustfmt]`.
+    pub fn new() -> Self {
+        Self {
+            stdout_level: log::Level::Info,
+            stderr_level: log::Level::Warn,
+            target: None,
+            prefix: None,
+        }
+    }
+
+    /// The level at which to log lines of `stdout`.
+    pub fn stdout_level(self, level: log::Level) -> Self {
+        Self {
+            stdout_level: level,
+            ..self
+        }
+    }
+
+    /// The level at which to log lines of `stderr`.
+    pub fn stderr_level(self, level: log::Level) -> Self {
+        Self {
+            stderr_level: level,
+            ..self
+        }
+    }
+
+    /// The target of log records, for filtering them, rather than this crate's.
+    pub fn target(self, target: impl Into<String>) -> Self {
+        Self {
+            target: Some(target.into()),
+            ..self
+        }
+    }
+
+    /// What to prefix each line with, like `[rustfmt]`, or nothing if empty.
+    pub fn prefix(self, prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: Some(prefix.into()),
+            ..self
+        }
+    }
+}
+
+impl Default for LogOutput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes `data` read from a child process' `stream` to the same stream of the parent.
+fn forward(stream: Stream, data: &[u8]) {
+    fn write_all(mut writer: impl Write, data: &[u8]) -> io::Result<()> {
+        writer.write_all(data)?;
+        writer.flush()
+    }
+
+    // Failing to show output is no reason to fail the child process, so ignore any errors.
+    let _ = match stream {
+        Stream::Stdout => write_all(io::stdout().lock(), data),
+        Stream::Stderr => write_all(io::stderr().lock(), data),
+    };
+}
+
+/// The specific error case encountered with [`EasyCommand::run_streaming`].
+#[derive(Debug, thiserror::Error)]
+pub enum RunStreamingErrorKind {
+    #[error(transparent)]
+    Run(#[from] RunErrorKind),
+    #[error("failed to read output")]
+    ReadOutput { source: std::io::Error },
+}
+
+/// Splits the chunks of output read from a stream into lines.
+#[derive(Debug, Default)]
+pub(crate) struct LineBuffer {
+    partial: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Hands each line completed by `data` to `on_line`, without its line ending, and keeps any
+    /// incomplete last line until more data is pushed.
+    pub fn push(&mut self, data: &[u8], mut on_line: impl FnMut(&str)) {
+        let mut rest = data;
+        while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+            let line = if self.partial.is_empty() {
+                &rest[..end]
+            } else {
+                self.partial.extend_from_slice(&rest[..end]);
+                &self.partial[..]
+            };
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            on_line(&String::from_utf8_lossy(line));
+            self.partial.clear();
+            rest = &rest[end + 1..];
+        }
+        self.partial.extend_from_slice(rest);
+    }
+
+    /// Hands any incomplete last line to `on_line`, for when the stream has closed.
+    pub fn finish(&mut self, mut on_line: impl FnMut(&str)) {
+        if !self.partial.is_empty() {
+            on_line(&String::from_utf8_lossy(&self.partial));
+            self.partial.clear();
+        }
+    }
+}
+
+/// Keeps the last bytes of a stream, up to a limit.
+#[derive(Debug)]
+pub(crate) struct TailBuffer {
+    bytes: Vec<u8>,
+    max_len: usize,
+}
+
+impl TailBuffer {
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            bytes: Vec::new(),
+            max_len,
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.bytes.extend_from_slice(data);
+        // Only drain occasionally, so that pushing stays cheap.
+        if self.bytes.len() > self.max_len.saturating_mul(2) {
+            self.bytes.drain(..self.bytes.len() - self.max_len);
+        }
+    }
+
+    pub fn contents(&self) -> &[u8] {
+        &self.bytes[self.bytes.len().saturating_sub(self.max_len)..]
+    }
+
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        self.bytes
+            .drain(..self.bytes.len().saturating_sub(self.max_len));
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::executor::{FakeExecutor, FakeResponse};
+
+    fn lines(chunks: &[&[u8]]) -> Vec<String> {
+        let mut buffer = LineBuffer::default();
+        let mut lines = Vec::new();
+        for chunk in chunks {
+            buffer.push(chunk, |line| lines.push(line.to_owned()));
+        }
+        buffer.finish(|line| lines.push(line.to_owned()));
+        lines
+    }
+
+    #[test]
+    fn line_buffer_carries_partial_lines_across_chunks() {
+        assert_eq!(lines(&[b"a\nb", b"c", b"d\ne\n"]), ["a", "bcd", "e"]);
+        assert_eq!(lines(&[b"a", b"\n", b"\n"]), ["a", ""]);
+    }
+
+    #[test]
+    fn line_buffer_strips_carriage_returns() {
+        assert_eq!(lines(&[b"a\r\nb\r", b"\nc\rd\n"]), ["a", "b", "c\rd"]);
+    }
+
+    #[test]
+    fn line_buffer_flushes_final_line_without_newline() {
+        let mut buffer = LineBuffer::default();
+        let mut lines = Vec::new();
+        buffer.push(b"a\nb", |line| lines.push(line.to_owned()));
+        assert_eq!(lines, ["a"]);
+        buffer.finish(|line| lines.push(line.to_owned()));
+        assert_eq!(lines, ["a", "b"]);
+        buffer.finish(|line| lines.push(line.to_owned()));
+        assert_eq!(lines, ["a", "b"]);
+    }
+
+    #[test]
+    fn line_buffer_replaces_invalid_utf8() {
+        // A multi-byte character split across chunks is still decoded.
+        assert_eq!(lines(&[b"a\xff\n\xc3", b"\xa9\n"]), ["a\u{fffd}", "é"]);
+    }
+
+    fn cmd_for(program: &str, response: FakeResponse) -> EasyCommand {
+        let fake = Arc::new(FakeExecutor::new());
+        fake.on_program(program, response);
+        let mut cmd = EasyCommand::new(program);
+        cmd.executor(fake);
+        cmd
+    }
+
+    fn cmd(response: FakeResponse) -> EasyCommand {
+        cmd_for("build", response)
+    }
+
+    #[test]
+    fn run_streaming_tags_lines_with_their_stream() {
+        let mut cmd = cmd(FakeResponse::success()
+            .stdout("compiling\ndone")
+            .stderr("warning: unused\n"));
+        let mut lines = Vec::new();
+        cmd.run_streaming(|stream, line| lines.push((stream, line.to_owned())))
+            .unwrap();
+        let of = |stream| {
+            lines
+                .iter()
+                .filter(|(s, _)| *s == stream)
+                .map(|(_, line)| line.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(of(Stream::Stdout), ["compiling", "done"]);
+        assert_eq!(of(Stream::Stderr), ["warning: unused"]);
+        assert_eq!(lines.len(), 3);
+    }
+
+    #[test]
+    fn tail_buffer_keeps_last_bytes() {
+        let mut buffer = TailBuffer::new(4);
+        buffer.push(b"ab");
+        assert_eq!(buffer.contents(), b"ab");
+        for chunk in [b"cde", b"fgh", b"ijk"] {
+            buffer.push(chunk);
+        }
+        assert_eq!(buffer.contents(), b"hijk");
+        assert_eq!(buffer.into_bytes(), b"hijk");
+    }
+
+    #[test]
+    fn output_capture_is_limited_but_forwarding_is_not() {
+        let mut cmd = cmd(FakeResponse::success()
+            .stdout("0123456789")
+            .stderr("abcdef"));
+        let child = cmd.spawn_with_stdio(None, None, None).unwrap();
+        let mut forwarded = (Vec::new(), Vec::new());
+        let output = child
+            .output_with(4, |stream, data| match stream {
+                Stream::Stdout => forwarded.0.extend_from_slice(data),
+                Stream::Stderr => forwarded.1.extend_from_slice(data),
+            })
+            .unwrap();
+        assert_eq!(output.stdout, b"6789");
+        assert_eq!(output.stderr, b"cdef");
+        assert_eq!(forwarded, (b"0123456789".to_vec(), b"abcdef".to_vec()));
+    }
+
+    #[test]
+    fn output_tee_shows_stderr_tail_on_failure() {
+        let output = cmd(FakeResponse::success().stdout("built\n"))
+            .output_tee()
+            .unwrap();
+        assert_eq!(output.stdout, b"built\n");
+
+        let e = cmd(FakeResponse::exit_code(1).stderr("error: missing semicolon\n"))
+            .output_tee()
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "failed to execute build; stderr:\n    error: missing semicolon"
+        );
+        match e.source {
+            OutputCheckedErrorKind::UnsuccessfulExitCode { output, .. } => {
+                assert_eq!(output.stderr, b"error: missing semicolon\n");
+            }
+            source => panic!("unexpected error: {source:?}"),
+        }
+    }
+
+    type Record = (String, log::Level, String);
+
+    /// Records every log record, from every test, as its target, level and message.
+    struct Logger(Mutex<Vec<Record>>);
+
+    impl log::Log for Logger {
+        fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record<'_>) {
+            let record = (
+                record.target().to_owned(),
+                record.level(),
+                record.args().to_string(),
+            );
+            self.0.lock().unwrap().push(record);
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Runs `cmd` with [`EasyCommand::run_logged`], returning the records it logged, sorted, as
+    /// told apart from those of other tests by `is_own`.
+    fn run_logged(
+        mut cmd: EasyCommand,
+        config: &LogOutput,
+        is_own: impl Fn(&Record) -> bool,
+    ) -> Vec<Record> {
+        static LOGGER: Logger = Logger(Mutex::new(Vec::new()));
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(log::LevelFilter::Trace);
+        }
+        cmd.run_logged(config).unwrap();
+        let mut records = LOGGER
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|record| is_own(record))
+            .cloned()
+            .collect::<Vec<_>>();
+        // Lines from different streams may be logged in either order.
+        records.sort();
+        records
+    }
+
+    fn logged_cmd(program: &str) -> EasyCommand {
+        cmd_for(
+            program,
+            FakeResponse::success().stdout("out\n").stderr("err\n"),
+        )
+    }
+
+    fn record(target: &str, level: log::Level, message: &str) -> Record {
+        (target.to_owned(), level, message.to_owned())
+    }
+
+    #[test]
+    fn run_logged_prefixes_lines_with_program_name_by_default() {
+        let records = run_logged(
+            logged_cmd("/opt/tools/logged-default"),
+            &LogOutput::new(),
+            |(_, _, message)| message.starts_with("[logged-default]"),
+        );
+        assert_eq!(
+            records,
+            [
+                record("ezcmd", log::Level::Warn, "[logged-default] err"),
+                record("ezcmd", log::Level::Info, "[logged-default] out"),
+            ]
+        );
+    }
+
+    #[test]
+    fn run_logged_uses_configured_levels_target_and_prefix() {
+        let config = LogOutput::new()
+            .stdout_level(log::Level::Debug)
+            .stderr_level(log::Level::Error)
+            .target("logged-custom")
+            .prefix("custom:");
+        let records = run_logged(logged_cmd("custom"), &config, |(target, ..)| {
+            target == "logged-custom"
+        });
+        assert_eq!(
+            records,
+            [
+                record("logged-custom", log::Level::Error, "custom: err"),
+                record("logged-custom", log::Level::Debug, "custom: out"),
+            ]
+        );
+
+        let config = LogOutput::new().target("logged-unprefixed").prefix("");
+        let records = run_logged(logged_cmd("unprefixed"), &config, |(target, ..)| {
+            target == "logged-unprefixed"
+        });
+        assert_eq!(
+            records,
+            [
+                record("logged-unprefixed", log::Level::Warn, "err"),
+                record("logged-unprefixed", log::Level::Info, "out"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use crate::Signal;
+
+/// How to stop a child process that must be stopped early, i.e., because it timed out.
+///
+/// By default, child processes are killed immediately, which gives them no chance to clean up.
+/// [`TerminationPolicy::graceful`] asks them to exit first, which lets tools like test runners and
+/// coverage collectors flush their output.
+///
+/// Signals are only supported on Unix. Elsewhere, child processes are always killed immediately.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TerminationPolicy {
+    pub(crate) signal: Option<Signal>,
+    pub(crate) grace: Duration,
+    pub(crate) escalate: bool,
+}
+
+impl TerminationPolicy {
+    /// Kill the child process immediately. This is the default.
+    pub const fn kill() -> Self {
+        Self {
+            signal: None,
+            grace: Duration::ZERO,
+            escalate: true,
+        }
+    }
+
+    /// Send [`Signal::TERM`] to the child process, and kill it if it has not exited after `grace`.
+    pub const fn graceful(grace: Duration) -> Self {
+        Self {
+            signal: Some(Signal::TERM),
+            grace,
+            escalate: true,
+        }
+    }
+
+    /// Send `signal` to the child process first, instead of [`Signal::TERM`].
+    pub const fn signal(self, signal: Signal) -> Self {
+        Self {
+            signal: Some(signal),
+            ..self
+        }
+    }
+
+    /// Whether to kill the child process if it has not exited after the grace period. If `false`,
+    /// the child process is waited upon until it exits of its own accord.
+    pub const fn escalate(self, escalate: bool) -> Self {
+        Self { escalate, ..self }
+    }
+}
+
+impl Default for TerminationPolicy {
+    fn default() -> Self {
+        Self::kill()
+    }
+}
+
+/// The stage of a [`TerminationPolicy`] that actually ended a child process.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TerminationStage {
+    /// The child process exited after being sent the policy's first signal.
+    Signal(Signal),
+    /// The child process was killed, either immediately or after the grace period elapsed.
+    Kill,
+    /// The child process could not be terminated; see logs for details.
+    Failed,
+}
+
+impl Display for TerminationStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Signal(signal) => write!(f, "terminated with {signal}"),
+            Self::Kill => write!(f, "killed"),
+            Self::Failed => write!(f, "failed to terminate"),
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    process::{ExitStatus, Stdio},
+    time::Duration,
+};
+
+use crate::{
+    child::{CollectError, Stream, WaitError},
+    DisplayExit, EasyCommand, ExecuteError, OutputTail, Signal, SpawnError, TerminationStage,
+    WriteInputError,
+};
+
+/// The `stdout` and `stderr` of a child process, combined in the order in which they were read.
+///
+/// Since the streams are read separately, the order of output written to both at nearly the same
+/// time may not be exactly that in which it was written.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Transcript {
+    chunks: Vec<TranscriptChunk>,
+}
+
+/// A contiguous part of a [`Transcript`] read from a single stream.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TranscriptChunk {
+    pub stream: Stream,
+    pub data: Vec<u8>,
+}
+
+impl Transcript {
+    fn push(&mut self, stream: Stream, data: &[u8]) {
+        match self.chunks.last_mut() {
+            Some(last) if last.stream == stream => last.data.extend_from_slice(data),
+            _ => self.chunks.push(TranscriptChunk {
+                stream,
+                data: data.to_owned(),
+            }),
+        }
+    }
+
+    /// The parts of this transcript, in order, where consecutive parts are from different streams.
+    pub fn chunks(&self) -> &[TranscriptChunk] {
+        &self.chunks
+    }
+
+    /// All output, from both streams, in order.
+    pub fn combined(&self) -> Vec<u8> {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| &chunk.data)
+            .copied()
+            .collect()
+    }
+
+    /// Only the output from `stream`.
+    pub fn stream(&self, stream: Stream) -> Vec<u8> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.stream == stream)
+            .flat_map(|chunk| &chunk.data)
+            .copied()
+            .collect()
+    }
+
+    /// Equivalent to `self.stream(Stream::Stdout)`.
+    pub fn stdout(&self) -> Vec<u8> {
+        self.stream(Stream::Stdout)
+    }
+
+    /// Equivalent to `self.stream(Stream::Stderr)`.
+    pub fn stderr(&self) -> Vec<u8> {
+        self.stream(Stream::Stderr)
+    }
+}
+
+/// Displays all output, with invalid UTF-8 replaced.
+impl Display for Transcript {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.combined()))
+    }
+}
+
+/// The output of [`EasyCommand::output_transcript`], like [`std::process::Output`], but with
+/// `stdout` and `stderr` combined into one [`Transcript`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TranscriptOutput {
+    pub status: ExitStatus,
+    pub transcript: Transcript,
+}
+
+impl EasyCommand {
+    fn output_transcript_impl(&mut self) -> Result<TranscriptOutput, OutputTranscriptErrorKind> {
+        log::debug!("getting output transcript from {self}…");
+        let mut child = self
+            .spawn_with_stdio(
+                Some(Stdio::null()),
+                Some(Stdio::piped()),
+                Some(Stdio::piped()),
+            )
+            .map_err(|source| OutputTranscriptErrorKind::Spawn { source })?;
+
+        log::trace!("waiting for output from `{}`…", child.cmd());
+        let mut transcript = Transcript::default();
+        let status = match child.wait_with_output(|stream, data| transcript.push(stream, data)) {
+            Ok(status) => status,
+            Err(CollectError::ReadOutput(source)) => {
+                return Err(OutputTranscriptErrorKind::ReadOutput { source })
+            }
+            Err(CollectError::Wait(WaitError::Wait(source))) => {
+                return Err(OutputTranscriptErrorKind::WaitForExitCode { source })
+            }
+            Err(CollectError::Wait(WaitError::TimedOut { elapsed, stage })) => {
+                return Err(OutputTranscriptErrorKind::TimedOut {
+                    elapsed,
+                    stage,
+                    transcript,
+                })
+            }
+            Err(CollectError::Wait(WaitError::WriteInput { status, source })) => {
+                return Err(OutputTranscriptErrorKind::WriteInput {
+                    source,
+                    output: TranscriptOutput { status, transcript },
+                })
+            }
+        };
+        log::debug!(
+            "received exit code {:?} from `{}`",
+            status.code(),
+            child.cmd()
+        );
+
+        if status.success() {
+            Ok(TranscriptOutput { status, transcript })
+        } else {
+            let (signal, core_dumped) = Signal::of_exit(&status);
+            Err(OutputTranscriptErrorKind::UnsuccessfulExitCode {
+                code: status.code(),
+                signal,
+                core_dumped,
+                transcript,
+            })
+        }
+    }
+
+    /// Execute this command, capturing its `stdout` and `stderr` together in one [`Transcript`],
+    /// and returning an error if it did not return a successful exit code.
+    ///
+    /// Like [`Self::output`], `stdin` is null. On failure, the returned error's [`Display`]
+    /// implementation includes the last few lines of the transcript.
+    pub fn output_transcript(
+        &mut self,
+    ) -> Result<TranscriptOutput, ExecuteError<OutputTranscriptErrorKind>> {
+        self.output_transcript_impl().map_err(|source| {
+            let output_tail = source
+                .transcript()
+                .and_then(|transcript| OutputTail::new("output", &transcript.combined()));
+            ExecuteError::new(self, source).with_output_tail(output_tail)
+        })
+    }
+}
+
+/// The specific error case encountered with [`EasyCommand::output_transcript`].
+#[derive(Debug, thiserror::Error)]
+pub enum OutputTranscriptErrorKind {
+    #[error("failed to spawn")]
+    Spawn { source: SpawnError },
+    #[error("failed to read output")]
+    ReadOutput { source: io::Error },
+    #[error("failed to wait for exit code")]
+    WaitForExitCode { source: io::Error },
+    /// The child process was stopped after running for longer than [`EasyCommand::timeout`].
+    /// `transcript` holds whatever output was read before then.
+    #[error("timed out after {elapsed:?} ({stage})")]
+    TimedOut {
+        elapsed: Duration,
+        stage: TerminationStage,
+        transcript: Transcript,
+    },
+    /// The child process exited successfully, but its [`Input`](crate::Input) could not be
+    /// written. `output` holds all of its output.
+    #[error("failed to write input")]
+    WriteInput {
+        source: WriteInputError,
+        output: TranscriptOutput,
+    },
+    /// Like [`RunErrorKind::UnsuccessfulExitCode`](crate::RunErrorKind::UnsuccessfulExitCode), but
+    /// with the captured `transcript`.
+    #[error("{}", DisplayExit { code: *code, signal: *signal, core_dumped: *core_dumped })]
+    UnsuccessfulExitCode {
+        code: Option<i32>,
+        signal: Option<Signal>,
+        core_dumped: bool,
+        transcript: Transcript,
+    },
+}
+
+impl OutputTranscriptErrorKind {
+    /// The output captured before this error, if any.
+    pub fn transcript(&self) -> Option<&Transcript> {
+        match self {
+            Self::TimedOut { transcript, .. } | Self::UnsuccessfulExitCode { transcript, .. } => {
+                Some(transcript)
+            }
+            Self::WriteInput { output, .. } => Some(&output.transcript),
+            Self::Spawn { .. } | Self::ReadOutput { .. } | Self::WaitForExitCode { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::executor::{FakeExecutor, FakeResponse};
+
+    fn transcript() -> Transcript {
+        let mut transcript = Transcript::default();
+        transcript.push(Stream::Stdout, b"a");
+        transcript.push(Stream::Stdout, b"b\n");
+        transcript.push(Stream::Stderr, b"c\n");
+        transcript.push(Stream::Stdout, b"d\n");
+        transcript.push(Stream::Stderr, b"e");
+        transcript.push(Stream::Stderr, b"\n");
+        transcript
+    }
+
+    #[test]
+    fn push_merges_chunks_from_same_stream() {
+        let chunk = |stream, data: &[u8]| TranscriptChunk {
+            stream,
+            data: data.to_owned(),
+        };
+        assert_eq!(
+            transcript().chunks(),
+            [
+                chunk(Stream::Stdout, b"ab\n"),
+                chunk(Stream::Stderr, b"c\n"),
+                chunk(Stream::Stdout, b"d\n"),
+                chunk(Stream::Stderr, b"e\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn transcript_separates_and_combines_streams() {
+        let transcript = transcript();
+        assert_eq!(transcript.stdout(), b"ab\nd\n");
+        assert_eq!(transcript.stderr(), b"c\ne\n");
+        assert_eq!(transcript.combined(), b"ab\nc\nd\ne\n");
+        assert_eq!(transcript.to_string(), "ab\nc\nd\ne\n");
+    }
+
+    fn cmd(response: FakeResponse) -> EasyCommand {
+        let fake = Arc::new(FakeExecutor::new());
+        fake.on_program("test", response);
+        let mut cmd = EasyCommand::new("test");
+        cmd.executor(fake);
+        cmd
+    }
+
+    #[test]
+    fn output_transcript_captures_both_streams() {
+        let output = cmd(FakeResponse::success().stdout("out\n").stderr("err\n"))
+            .output_transcript()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.transcript.stdout(), b"out\n");
+        assert_eq!(output.transcript.stderr(), b"err\n");
+    }
+
+    #[test]
+    fn output_transcript_shows_tail_on_failure() {
+        let e = cmd(FakeResponse::exit_code(2).stdout("checking\nfailed\n"))
+            .output_transcript()
+            .unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "failed to execute test; output:\n    checking\n    failed"
+        );
+        match e.source {
+            OutputTranscriptErrorKind::UnsuccessfulExitCode {
+                code, transcript, ..
+            } => {
+                assert_eq!(code, Some(2));
+                assert_eq!(transcript.stdout(), b"checking\nfailed\n");
+            }
+            source => panic!("unexpected error: {source:?}"),
+        }
+    }
+}
@@ -0,0 +1,447 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+    num::ParseIntError,
+    process::Output,
+    str::FromStr,
+};
+
+use regex_lite::Regex;
+
+use crate::{EasyCommand, ExecuteError, OutputCheckedErrorKind};
+
+/// A semver-like version of a program, like `1.70.0`.
+///
+/// Versions with fewer than three components, like `1.70`, are parsed with the missing components
+/// taken to be `0`. Anything following the `patch` component, like `-nightly`, is ignored.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Self {
+            major,
+            minor,
+            patch,
+        } = self;
+        write!(f, "{major}.{minor}.{patch}")
+    }
+}
+
+impl FromStr for Version {
+    type Err = ParseVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |source| ParseVersionError {
+            text: s.to_owned(),
+            source,
+        };
+        let end = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let mut components = s[..end].trim_end_matches('.').split('.');
+        let mut next = || components.next().map(u64::from_str).transpose();
+        let major = next()
+            .map_err(|e| invalid(Some(e)))?
+            .ok_or_else(|| invalid(None))?;
+        let minor = next().map_err(|e| invalid(Some(e)))?.unwrap_or(0);
+        let patch = next().map_err(|e| invalid(Some(e)))?.unwrap_or(0);
+        Ok(Self::new(major, minor, patch))
+    }
+}
+
+/// An error encountered when parsing a [`Version`].
+#[derive(Debug, thiserror::Error)]
+#[error("{text:?} is not a version like `1.70.0`")]
+pub struct ParseVersionError {
+    text: String,
+    source: Option<ParseIntError>,
+}
+
+/// A requirement that a [`Version`] must satisfy, like `>=1.70`.
+///
+/// A requirement is a comma-separated list of comparisons, all of which must be satisfied, like
+/// `>=1.70, <2`. Each comparison is one of `=`, `>`, `>=`, `<`, or `<=`, followed by a version.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VersionReq {
+    text: String,
+    comparators: Vec<Comparator>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Comparator {
+    ordering: Ordering,
+    or_equal: bool,
+    version: Version,
+}
+
+impl Comparator {
+    const OPERATORS: [(&'static str, Ordering, bool); 5] = [
+        (">=", Ordering::Greater, true),
+        ("<=", Ordering::Less, true),
+        (">", Ordering::Greater, false),
+        ("<", Ordering::Less, false),
+        ("=", Ordering::Equal, false),
+    ];
+}
+
+impl VersionReq {
+    /// Whether `version` satisfies this requirement.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.comparators.iter().all(|comparator| {
+            let ordering = version.cmp(&comparator.version);
+            ordering == comparator.ordering || (comparator.or_equal && ordering == Ordering::Equal)
+        })
+    }
+}
+
+impl Display for VersionReq {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = ParseVersionReqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let comparators = s
+            .split(',')
+            .map(|comparator| {
+                let comparator = comparator.trim();
+                let (ordering, or_equal, version) = Comparator::OPERATORS
+                    .iter()
+                    .find_map(|&(operator, ordering, or_equal)| {
+                        Some((ordering, or_equal, comparator.strip_prefix(operator)?))
+                    })
+                    .ok_or_else(|| ParseVersionReqError::MissingOperator {
+                        comparator: comparator.to_owned(),
+                    })?;
+                Ok(Comparator {
+                    ordering,
+                    or_equal,
+                    version: version.trim().parse()?,
+                })
+            })
+            .collect::<Result<_, ParseVersionReqError>>()?;
+        Ok(Self {
+            text: s.trim().to_owned(),
+            comparators,
+        })
+    }
+}
+
+/// An error encountered when parsing a [`VersionReq`].
+#[derive(Debug, thiserror::Error)]
+pub enum ParseVersionReqError {
+    #[error("{comparator:?} does not start with one of `=`, `>`, `>=`, `<`, or `<=`")]
+    MissingOperator { comparator: String },
+    #[error(transparent)]
+    Version(#[from] ParseVersionError),
+}
+
+/// How to find the version of a program in the output of an [`EasyCommand`], and what version to
+/// require, for use with [`EasyCommand::version_with`].
+#[derive(Clone, Debug)]
+pub struct VersionQuery {
+    pattern: Regex,
+    requirement: Option<VersionReq>,
+}
+
+impl VersionQuery {
+    const DEFAULT_PATTERN: &'static str = r"\d+\.\d+(\.\d+)?";
+
+    /// Find the first version in output like `1.70` or `1.70.0`, without any requirement.
+    pub fn new() -> Self {
+        Self {
+            pattern: Regex::new(Self::DEFAULT_PATTERN).unwrap(),
+            requirement: None,
+        }
+    }
+
+    /// Find the version with the regular expression `pattern`, rather than the default.
+    ///
+    /// The version is taken from the capture group named `version`, if there is one, or else the
+    /// whole match. For example, `cmake version (?<version>\S+)`.
+    pub fn pattern(self, pattern: &str) -> Result<Self, regex_lite::Error> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            ..self
+        })
+    }
+
+    /// Fail unless the version found satisfies `requirement`.
+    pub fn requirement(self, requirement: VersionReq) -> Self {
+        Self {
+            requirement: Some(requirement),
+            ..self
+        }
+    }
+
+    fn find(&self, raw_output: &str) -> Result<Version, VersionErrorKind> {
+        let not_found = || VersionErrorKind::NotFound {
+            pattern: self.pattern.to_string(),
+            raw_output: raw_output.to_owned(),
+        };
+        let captures = self.pattern.captures(raw_output).ok_or_else(not_found)?;
+        let text = captures
+            .name("version")
+            .or_else(|| captures.get(0))
+            .ok_or_else(not_found)?
+            .as_str();
+        let version = text
+            .parse()
+            .map_err(|source| VersionErrorKind::InvalidVersion {
+                source,
+                raw_output: raw_output.to_owned(),
+            })?;
+        match &self.requirement {
+            Some(requirement) if !requirement.matches(&version) => {
+                Err(VersionErrorKind::Unsatisfied {
+                    version,
+                    requirement: requirement.clone(),
+                    raw_output: raw_output.to_owned(),
+                })
+            }
+            _ => Ok(version),
+        }
+    }
+}
+
+impl Default for VersionQuery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EasyCommand {
+    fn version_impl(&mut self, query: &VersionQuery) -> Result<Version, VersionErrorKind> {
+        let Output { stdout, stderr, .. } = self.output_checked_impl()?;
+        // Some programs, like `java -version`, report their version on `stderr`.
+        let raw_output = if stdout.iter().all(u8::is_ascii_whitespace) {
+            String::from_utf8_lossy(&stderr)
+        } else {
+            String::from_utf8_lossy(&stdout)
+        };
+        let version = query.find(raw_output.trim())?;
+        log::debug!("found version {version} of {self}");
+        Ok(version)
+    }
+
+    /// Execute this command, which should query the version of its program, like `git --version`,
+    /// and find the version in its output, as configured by `query`.
+    ///
+    /// The version is searched for in `stdout`, or in `stderr` if `stdout` is empty.
+    pub fn version_with(
+        &mut self,
+        query: &VersionQuery,
+    ) -> Result<Version, ExecuteError<VersionErrorKind>> {
+        self.version_impl(query).map_err(|source| {
+            let stderr_tail = match &source {
+                VersionErrorKind::OutputChecked(source) => source.stderr_tail(),
+                _ => None,
+            };
+            ExecuteError::new(self, source).with_output_tail(stderr_tail)
+        })
+    }
+
+    /// Like [`Self::version_with`], with the default [`VersionQuery`].
+    pub fn version(&mut self) -> Result<Version, ExecuteError<VersionErrorKind>> {
+        self.version_with(&VersionQuery::new())
+    }
+
+    /// Like [`Self::version`], but returning an error if the version found does not satisfy
+    /// `requirement`.
+    pub fn require_version(
+        &mut self,
+        requirement: &VersionReq,
+    ) -> Result<Version, ExecuteError<VersionErrorKind>> {
+        self.version_with(&VersionQuery::new().requirement(requirement.clone()))
+    }
+}
+
+/// The specific error case encountered with [`EasyCommand::version_with`] and related methods.
+#[derive(Debug, thiserror::Error)]
+pub enum VersionErrorKind {
+    #[error(transparent)]
+    OutputChecked(#[from] OutputCheckedErrorKind),
+    #[error(
+        "found no version matching `{pattern}` in output{}",
+        DisplayRawOutput(raw_output)
+    )]
+    NotFound { pattern: String, raw_output: String },
+    #[error("found an invalid version in output{}", DisplayRawOutput(raw_output))]
+    InvalidVersion {
+        source: ParseVersionError,
+        raw_output: String,
+    },
+    /// The version found did not satisfy the requirement given with
+    /// [`VersionQuery::requirement`].
+    #[error(
+        "version {version} does not satisfy requirement `{requirement}`; found in output{}",
+        DisplayRawOutput(raw_output)
+    )]
+    Unsatisfied {
+        version: Version,
+        requirement: VersionReq,
+        raw_output: String,
+    },
+}
+
+/// Displays the output in which a version was searched for, indented on the following lines.
+struct DisplayRawOutput<'a>(&'a str);
+
+impl Display for DisplayRawOutput<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Self(raw_output) = self;
+        if raw_output.is_empty() {
+            return write!(f, " (empty)");
+        }
+        write!(f, ":")?;
+        for line in raw_output.lines() {
+            write!(f, "\n    {line}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::executor::{FakeExecutor, FakeResponse};
+
+    fn version(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    fn req(s: &str) -> VersionReq {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn version_parses_missing_components_as_zero() {
+        assert_eq!(version("1"), Version::new(1, 0, 0));
+        assert_eq!(version("1.70"), Version::new(1, 70, 0));
+        assert_eq!(version("1.70."), Version::new(1, 70, 0));
+        assert_eq!(version("1.70.2"), Version::new(1, 70, 2));
+    }
+
+    #[test]
+    fn version_ignores_suffix() {
+        assert_eq!(version("1.70.0-nightly"), Version::new(1, 70, 0));
+        assert_eq!(version("2.39.2.windows.1"), Version::new(2, 39, 2));
+    }
+
+    #[test]
+    fn version_rejects_non_versions() {
+        assert!("".parse::<Version>().is_err());
+        assert!("v1.70".parse::<Version>().is_err());
+        assert!("1..2".parse::<Version>().is_err());
+        assert!("99999999999999999999".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn version_req_matches_all_comparators() {
+        let req = req(">=1.70, <2");
+        assert!(req.matches(&version("1.70.0")));
+        assert!(req.matches(&version("1.99.9")));
+        assert!(!req.matches(&version("1.69.9")));
+        assert!(!req.matches(&version("2.0.0")));
+        assert_eq!(req.to_string(), ">=1.70, <2");
+    }
+
+    #[test]
+    fn version_req_supports_each_operator() {
+        let v = version("1.2.3");
+        assert!(req("=1.2.3").matches(&v));
+        assert!(!req("=1.2").matches(&v));
+        assert!(req(">1.2").matches(&v) && !req(">1.2.3").matches(&v));
+        assert!(req("<1.3").matches(&v) && !req("<1.2.3").matches(&v));
+        assert!(req("<=1.2.3").matches(&v) && !req("<=1.2.2").matches(&v));
+        assert!(req(">= 1.2.3").matches(&v));
+    }
+
+    #[test]
+    fn version_req_rejects_invalid_requirements() {
+        assert!(matches!(
+            "1.70".parse::<VersionReq>(),
+            Err(ParseVersionReqError::MissingOperator { .. })
+        ));
+        assert!(matches!(
+            ">=x".parse::<VersionReq>(),
+            Err(ParseVersionReqError::Version(_))
+        ));
+        assert!(matches!(
+            ">=1.70,".parse::<VersionReq>(),
+            Err(ParseVersionReqError::MissingOperator { .. })
+        ));
+    }
+
+    #[test]
+    fn query_finds_version_by_pattern() {
+        let query = VersionQuery::new();
+        assert_eq!(query.find("git version 2.39.2").unwrap(), version("2.39.2"));
+        assert!(matches!(
+            query.find("unknown"),
+            Err(VersionErrorKind::NotFound { .. })
+        ));
+
+        let query = VersionQuery::new()
+            .pattern(r"cmake version (?<version>\S+)")
+            .unwrap();
+        assert_eq!(
+            query.find("cmake 1.0\ncmake version 3.27.1").unwrap(),
+            version("3.27.1")
+        );
+    }
+
+    fn fake_version(response: FakeResponse) -> EasyCommand {
+        let fake = Arc::new(FakeExecutor::new());
+        fake.on_program("tool", response);
+        let mut cmd = EasyCommand::simple("tool", ["--version"]);
+        cmd.executor(fake);
+        cmd
+    }
+
+    #[test]
+    fn version_falls_back_to_stderr() {
+        let mut cmd = fake_version(FakeResponse::success().stderr("tool 1.8.0_392\n"));
+        assert_eq!(cmd.version().unwrap(), version("1.8.0"));
+        let mut cmd = fake_version(
+            FakeResponse::success()
+                .stdout("tool 2.0\n")
+                .stderr("warning: 1.0\n"),
+        );
+        assert_eq!(cmd.version().unwrap(), version("2.0"));
+    }
+
+    #[test]
+    fn unsatisfied_requirement_shows_command_version_and_output() {
+        let mut cmd = fake_version(FakeResponse::success().stdout("tool 1.69.0\n"));
+        let e = cmd.require_version(&req(">=1.70")).unwrap_err();
+        assert!(matches!(
+            e.source,
+            VersionErrorKind::Unsatisfied { version: v, .. } if v == version("1.69.0")
+        ));
+        let message = format!("{e}: {}", e.source);
+        for expected in ["tool --version", "1.69.0", ">=1.70", "\n    tool 1.69.0"] {
+            assert!(message.contains(expected), "{message}");
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! Tests of [`DryRun::set_global`], which are kept in their own test binary, since a global dry
+//! run would apply to every other test running at the same time.
+
+use std::sync::Arc;
+
+use ezcmd::{
+    executor::{FakeExecutor, FakeResponse},
+    DryRun, EasyCommand,
+};
+
+#[test]
+fn global_dry_run_has_lowest_precedence() {
+    let fake = Arc::new(FakeExecutor::new());
+    fake.on_program("deploy", FakeResponse::success().stdout("executed"));
+    let mut cmd = EasyCommand::new("deploy");
+    cmd.executor(fake.clone());
+    let stdout = |cmd: &mut EasyCommand| cmd.output().unwrap().stdout;
+
+    DryRun::set_global(Some(DryRun::new().stdout("global")));
+    assert_eq!(stdout(&mut cmd), b"global");
+    DryRun::new().stdout("scoped").scope(|| {
+        assert_eq!(stdout(&mut cmd), b"scoped");
+    });
+    let mut own = EasyCommand::new("deploy");
+    own.dry_run(DryRun::new().stdout("own"));
+    assert_eq!(stdout(&mut own), b"own");
+    assert!(fake.executed().is_empty());
+
+    DryRun::set_global(None);
+    assert_eq!(stdout(&mut cmd), b"executed");
+    assert_eq!(fake.command_lines(), ["deploy"]);
+}